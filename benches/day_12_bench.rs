@@ -0,0 +1,106 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use itertools::Itertools;
+
+use advent_of_code_2023::solutions::*;
+
+/// Run this benchmark using
+/// ```shell
+/// $ cargo bench --bench day_12_bench
+/// ```
+fn day12(c: &mut Criterion) {
+    let input = include_str!("../inputs/day_12.txt")
+        .trim()
+        .lines()
+        .collect_vec();
+    let mut group = c.benchmark_group("day12");
+
+    group.bench_function("part1", |b| {
+        b.iter(|| day_12::solve_1(input.clone()));
+    });
+
+    group.bench_function("part2", |b| {
+        b.iter(|| day_12::solve_2(input.clone()));
+    });
+
+    for unfold in [1, 5, 10, 20] {
+        group.bench_with_input(BenchmarkId::new("unfold", unfold), &unfold, |b, &u| {
+            b.iter(|| day_12::solve(input.clone(), u));
+        });
+    }
+    group.finish();
+
+    // Baseline: trying every way to fill in the unknown springs, on the sample where that is still feasible
+    let sample = [
+        "???.### 1,1,3",
+        ".??..??...?##. 1,1,3",
+        "?#?#?#?#?#?#?#? 1,3,1,6",
+        "????.#...#... 4,1,1",
+        "????.######..#####. 1,6,5",
+        "?###???????? 3,2,1",
+    ];
+    let mut group = c.benchmark_group("day12_baseline");
+
+    for unfold in [1, 2] {
+        let records = sample
+            .iter()
+            .map(|r| day_12::Record::new(r).unfold(unfold))
+            .collect_vec();
+        let unfolded = sample
+            .iter()
+            .map(|r| unfold_record(r, unfold))
+            .collect_vec();
+        assert_eq!(
+            records.iter().map(|r| r.nr_arrangements()).collect_vec(),
+            unfolded
+                .iter()
+                .map(|(s, c)| brute_force(s, c))
+                .collect_vec()
+        );
+
+        group.bench_with_input(BenchmarkId::new("dp", unfold), &records, |b, records| {
+            b.iter(|| records.iter().map(|r| r.nr_arrangements()).sum::<u64>());
+        });
+        group.bench_with_input(
+            BenchmarkId::new("brute_force", unfold),
+            &unfolded,
+            |b, unfolded| {
+                b.iter(|| unfolded.iter().map(|(s, c)| brute_force(s, c)).sum::<u64>());
+            },
+        );
+    }
+}
+
+fn unfold_record(record: &str, unfold: usize) -> (Vec<u8>, Vec<usize>) {
+    let (springs, criteria) = record.split_once(' ').unwrap();
+    let springs = vec![springs; unfold].join("?").into_bytes();
+    let criteria = vec![criteria; unfold]
+        .join(",")
+        .split(',')
+        .map(|c| c.parse().unwrap())
+        .collect();
+
+    (springs, criteria)
+}
+
+fn brute_force(springs: &[u8], criteria: &[usize]) -> u64 {
+    let unknown = springs.iter().positions(|&s| s == b'?').collect_vec();
+
+    (0..1_u64 << unknown.len())
+        .filter(|mask| {
+            let mut filled = springs.to_vec();
+            unknown.iter().enumerate().for_each(|(bit, &i)| {
+                filled[i] = if mask >> bit & 1 == 1 { b'#' } else { b'.' };
+            });
+
+            let groups = filled
+                .split(|&s| s == b'.')
+                .map(|g| g.len())
+                .filter(|&len| len > 0)
+                .collect_vec();
+            groups == criteria
+        })
+        .count() as u64
+}
+
+criterion_group!(benches, day12);
+criterion_main!(benches);
@@ -0,0 +1,27 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use itertools::Itertools;
+
+use advent_of_code_2023::solutions::*;
+
+/// Run this benchmark using
+/// ```shell
+/// $ cargo bench --bench day_14_bench
+/// ```
+fn day14(c: &mut Criterion) {
+    let input = include_str!("../inputs/day_14.txt")
+        .trim()
+        .lines()
+        .collect_vec();
+    let mut group = c.benchmark_group("day14");
+
+    group.bench_function("part1", |b| {
+        b.iter(|| day_14::solve_1(input.clone()));
+    });
+
+    group.bench_function("part2", |b| {
+        b.iter(|| day_14::solve_2(input.clone()));
+    });
+}
+
+criterion_group!(benches, day14);
+criterion_main!(benches);
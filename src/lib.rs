@@ -1,2 +1,2 @@
 pub mod solutions;
-mod util;
+pub mod util;
@@ -1,59 +1,172 @@
-use std::str::FromStr;
-
-use regex::Regex;
+use std::collections::VecDeque;
+
+use rustc_hash::FxHashMap;
+
+/// The digits themselves, as a dictionary for the [`Matcher`]
+pub const DIGITS: [(&str, u32); 9] = [
+    ("1", 1),
+    ("2", 2),
+    ("3", 3),
+    ("4", 4),
+    ("5", 5),
+    ("6", 6),
+    ("7", 7),
+    ("8", 8),
+    ("9", 9),
+];
+
+/// The digits spelled out in English, as a dictionary for the [`Matcher`]
+pub const ENGLISH_WORDS: [(&str, u32); 9] = [
+    ("one", 1),
+    ("two", 2),
+    ("three", 3),
+    ("four", 4),
+    ("five", 5),
+    ("six", 6),
+    ("seven", 7),
+    ("eight", 8),
+    ("nine", 9),
+];
 
 pub fn solve_1(document: Vec<&str>) -> u32 {
-    let re = Regex::new(r"([1-9])").unwrap();
-
-    solve(&document, &re, &re)
+    solve(&document, &Matcher::new(&DIGITS)).unwrap()
 }
 
 pub fn solve_2(document: Vec<&str>) -> u32 {
-    let re = Regex::new(r"([1-9]|one|two|three|four|five|six|seven|eight|nine)").unwrap();
-    let re_rev = Regex::new(r"([1-9]|enin|thgie|neves|xis|evif|ruof|eerht|owt|eno)").unwrap();
+    solve(&document, &Matcher::new(&[DIGITS, ENGLISH_WORDS].concat())).unwrap()
+}
 
-    solve(&document, &re, &re_rev)
+/// Sums the calibration values of all lines, failing on the first line without any digit
+pub fn solve(document: &[&str], matcher: &Matcher) -> Result<u32, String> {
+    document.iter().map(|line| calibrate(line, matcher)).sum()
 }
 
-fn solve(document: &[&str], re: &Regex, re_rev: &Regex) -> u32 {
-    document
-        .iter()
-        .map(|&line| {
-            let first = re.find(line).unwrap().as_str();
-            let last = &reverse(re_rev.find(&reverse(line)).unwrap().as_str());
+/// Combines the first and the last digit of the line into a two-digit number
+pub fn calibrate(line: &str, matcher: &Matcher) -> Result<u32, String> {
+    let (first, last) = matcher
+        .first_and_last(line)
+        .ok_or_else(|| format!("No digit found in \"{line}\""))?;
 
-            Calibration::new(first, last)
-        })
-        .map(|calibration| calibration.number)
-        .sum()
+    Ok(first.value * 10 + last.value)
 }
 
-fn reverse(string: &str) -> String {
-    string.chars().rev().collect()
+/// Occurrence of a dictionary word at `start..end` of the text
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Match {
+    pub start: usize,
+    pub end: usize,
+    pub value: u32,
 }
 
-struct Calibration {
-    number: u32,
+/// Aho-Corasick automaton finding all (possibly overlapping) occurrences of the dictionary's words
+#[derive(Debug)]
+pub struct Matcher {
+    nodes: Vec<Node>,
 }
 
-impl Calibration {
-    fn new(first: &str, last: &str) -> Calibration {
-        let number = Self::parse_number(first) * 10 + Self::parse_number(last);
-        Calibration { number }
+#[derive(Debug, Default)]
+struct Node {
+    children: FxHashMap<u8, usize>,
+    /// The node for the longest proper suffix of this node that is also in the trie
+    fail: usize,
+    /// The length and value of the word ending at this node
+    word: Option<(usize, u32)>,
+    /// The node for the longest proper suffix of this node that is a word
+    next_word: Option<usize>,
+}
+
+impl Matcher {
+    const ROOT: usize = 0;
+
+    pub fn new(dictionary: &[(&str, u32)]) -> Matcher {
+        let mut nodes = vec![Node::default()];
+
+        for &(word, value) in dictionary {
+            let mut node = Self::ROOT;
+            for &b in word.as_bytes() {
+                node = match nodes[node].children.get(&b) {
+                    Some(&child) => child,
+                    None => {
+                        let child = nodes.len();
+                        nodes.push(Node::default());
+                        nodes[node].children.insert(b, child);
+                        child
+                    }
+                };
+            }
+            nodes[node].word = Some((word.len(), value));
+        }
+
+        // Children of the root fail back to it, everything deeper is linked breadth first
+        let mut queue: VecDeque<_> = nodes[Self::ROOT].children.values().copied().collect();
+        while let Some(node) = queue.pop_front() {
+            let children: Vec<_> = nodes[node].children.iter().map(|(&b, &c)| (b, c)).collect();
+
+            for (b, child) in children {
+                let fail = Self::step(&nodes, nodes[node].fail, b);
+                nodes[child].fail = fail;
+                nodes[child].next_word = match nodes[fail].word {
+                    Some(_) => Some(fail),
+                    None => nodes[fail].next_word,
+                };
+                queue.push_back(child);
+            }
+        }
+
+        Matcher { nodes }
+    }
+
+    /// All occurrences, ordered by where they end (and from the longest to the shortest when ending together)
+    pub fn matches<'a>(&'a self, text: &'a str) -> impl Iterator<Item = Match> + 'a {
+        text.bytes()
+            .enumerate()
+            .scan(Self::ROOT, |node, (i, b)| {
+                *node = Self::step(&self.nodes, *node, b);
+                Some((i + 1, *node))
+            })
+            .flat_map(move |(end, node)| {
+                let first = match self.nodes[node].word {
+                    Some(_) => Some(node),
+                    None => self.nodes[node].next_word,
+                };
+
+                std::iter::successors(first, |&n| self.nodes[n].next_word).map(move |n| {
+                    let (len, value) = self.nodes[n].word.unwrap();
+                    Match {
+                        start: end - len,
+                        end,
+                        value,
+                    }
+                })
+            })
     }
 
-    fn parse_number(number: &str) -> u32 {
-        match number {
-            "one" => 1,
-            "two" => 2,
-            "three" => 3,
-            "four" => 4,
-            "five" => 5,
-            "six" => 6,
-            "seven" => 7,
-            "eight" => 8,
-            "nine" => 9,
-            other => u32::from_str(other).unwrap(),
+    /// The match starting first and the one ending last, found in a single pass
+    ///
+    /// Ties go to the longest word, both matches may be the same one.
+    pub fn first_and_last(&self, text: &str) -> Option<(Match, Match)> {
+        self.matches(text).fold(None, |found, m| match found {
+            None => Some((m, m)),
+            Some((first, last)) => Some((
+                if (m.start, first.end) < (first.start, m.end) {
+                    m
+                } else {
+                    first
+                },
+                if m.end > last.end { m } else { last },
+            )),
+        })
+    }
+
+    fn step(nodes: &[Node], mut node: usize, b: u8) -> usize {
+        loop {
+            if let Some(&child) = nodes[node].children.get(&b) {
+                return child;
+            }
+            if node == Self::ROOT {
+                return Self::ROOT;
+            }
+            node = nodes[node].fail;
         }
     }
 }
@@ -97,4 +210,40 @@ mod tests {
 
         assert_eq!(54_518, solve_2(input));
     }
+
+    #[test]
+    fn day_01_matcher() {
+        let matcher = Matcher::new(&[DIGITS, ENGLISH_WORDS].concat());
+
+        assert_eq!(
+            vec![(0, 5, 8), (4, 7, 2), (7, 12, 3)],
+            matcher
+                .matches("eightwothree")
+                .map(|m| (m.start, m.end, m.value))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(Ok(18), calibrate("oneight", &matcher));
+        assert_eq!(Ok(77), calibrate("seven", &matcher));
+        assert_eq!(
+            Err("No digit found in \"abc\"".to_string()),
+            calibrate("abc", &matcher)
+        );
+        assert!(solve(&["1abc2", "xyz"], &Matcher::new(&DIGITS)).is_err());
+
+        let with_zero = Matcher::new(&[&DIGITS[..], &ENGLISH_WORDS[..], &[("zero", 0)]].concat());
+        assert_eq!(Ok(1), calibrate("zerone", &with_zero));
+
+        let dutch = Matcher::new(&[("een", 1), ("twee", 2), ("drie", 3)]);
+        assert_eq!(Ok(21), calibrate("tweeen", &dutch));
+        assert_eq!(Ok(31), calibrate("xdriexeenx", &dutch));
+
+        // The longest word wins when several start (or end) at the same place
+        let nested = Matcher::new(&[("b", 1), ("bcd", 2), ("abcd", 3), ("cd", 4)]);
+        let first = |text| nested.first_and_last(text).unwrap().0.value;
+        let last = |text| nested.first_and_last(text).unwrap().1.value;
+        assert_eq!(2, first("xbcd"));
+        assert_eq!(3, first("abcd"));
+        assert_eq!(3, last("abcd"));
+        assert_eq!(2, last("xbcd"));
+    }
 }
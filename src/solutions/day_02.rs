@@ -1,73 +1,165 @@
+use std::fmt::{Display, Formatter};
 use std::str::FromStr;
 
 use itertools::Itertools;
-use regex::Regex;
-use rustc_hash::FxHashMap;
+
+const BAG: &str = "12 red, 13 green, 14 blue";
 
 pub fn solve_1(games: Vec<&str>) -> u32 {
+    let bag = Bag::new(BAG);
+
     games
         .iter()
         .map(|g| Game::new(g))
-        .filter(|g| g.is_possible())
+        .filter(|g| g.is_possible(&bag))
         .map(|g| g.id)
         .sum()
 }
 
 pub fn solve_2(games: Vec<&str>) -> u32 {
-    games.iter().map(|g| Game::new(g)).map(|g| g.power()).sum()
+    let bag = Bag::new(BAG);
+
+    games
+        .iter()
+        .map(|g| Game::new(g))
+        .map(|g| g.minimal_bag(&bag).power())
+        .sum()
+}
+
+/// Cubes of any number of colours, written as in "3 blue, 4 red"
+///
+/// Both the bag's contents and every handful drawn from it are described this way.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Bag {
+    cubes: Vec<(String, u32)>,
+}
+
+impl Bag {
+    pub fn new(spec: &str) -> Bag {
+        let mut bag = Bag { cubes: vec![] };
+        spec.split(',')
+            .map(|c| c.trim())
+            .filter(|c| !c.is_empty())
+            .for_each(|c| {
+                let Some((count, colour)) = c
+                    .split_once(' ')
+                    .and_then(|(count, colour)| Some((u32::from_str(count).ok()?, colour)))
+                    .filter(|(_, colour)| colour.chars().all(char::is_alphanumeric))
+                else {
+                    panic!("Invalid cubes: {}", c);
+                };
+                bag.merge(colour, count, |a, b| a + b);
+            });
+
+        bag
+    }
+
+    /// How many cubes of the given colour there are, zero for unknown colours
+    pub fn count(&self, colour: &str) -> u32 {
+        self.cubes
+            .iter()
+            .find(|(c, _)| c == colour)
+            .map_or(0, |&(_, count)| count)
+    }
+
+    pub fn colours(&self) -> impl Iterator<Item = &str> + '_ {
+        self.cubes.iter().map(|(c, _)| c.as_str())
+    }
+
+    /// The product of the counts of all colours in the bag
+    pub fn power(&self) -> u32 {
+        self.cubes.iter().map(|&(_, count)| count).product()
+    }
+
+    /// Combines the count of an already known colour, otherwise adds it at the end
+    fn merge(&mut self, colour: &str, count: u32, combine: fn(u32, u32) -> u32) {
+        match self.cubes.iter_mut().find(|(c, _)| c == colour) {
+            Some((_, known)) => *known = combine(*known, count),
+            None => self.cubes.push((colour.to_string(), count)),
+        }
+    }
+}
+
+impl Display for Bag {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let cubes = self
+            .cubes
+            .iter()
+            .map(|(colour, count)| format!("{count} {colour}"))
+            .join(", ");
+
+        write!(f, "{cubes}")
+    }
 }
 
 #[derive(Debug)]
-struct Game {
+pub struct Game {
     id: u32,
-    max_red: u32,
-    max_green: u32,
-    max_blue: u32,
+    draws: Vec<Bag>,
+}
+
+/// A draw that holds more cubes of a colour than the bag does
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Violation {
+    /// The index of the draw, within its game
+    pub draw: usize,
+    pub colour: String,
+    pub drawn: u32,
+    pub available: u32,
 }
 
 impl Game {
-    const MAX_RED: u32 = 12;
-    const MAX_GREEN: u32 = 13;
-    const MAX_BLUE: u32 = 14;
-
-    fn new(game: &str) -> Game {
-        let id = u32::from_str(
-            &Regex::new(r"Game (?<id>\d+)")
-                .unwrap()
-                .captures(game)
-                .unwrap()["id"],
-        )
-        .unwrap();
-
-        let max_draw: FxHashMap<_, _> = Regex::new(r"(\d+ (?:red|green|blue))")
-            .unwrap()
-            .find_iter(game)
-            .map(|m| m.as_str())
-            .map(|colour_grab| {
-                let nr_to_colour: Vec<_> = colour_grab.split(' ').collect();
-                (nr_to_colour[1], u32::from_str(nr_to_colour[0]).unwrap())
-            })
-            .into_group_map()
-            .iter()
-            .map(|(&k, v)| (k, *v.iter().max().unwrap()))
-            .collect();
-
-        Game {
-            id,
-            max_red: *max_draw.get("red").unwrap_or(&0),
-            max_green: *max_draw.get("green").unwrap_or(&0),
-            max_blue: *max_draw.get("blue").unwrap_or(&0),
-        }
+    pub fn new(game: &str) -> Game {
+        let Some((header, draws)) = game.split_once(": ") else {
+            panic!("Invalid game: {}", game);
+        };
+
+        let id = u32::from_str(header.trim_start_matches("Game ")).unwrap();
+        let draws = draws.split(';').map(Bag::new).collect();
+
+        Game { id, draws }
+    }
+
+    pub fn id(&self) -> u32 {
+        self.id
     }
 
-    fn is_possible(&self) -> bool {
-        self.max_red <= Self::MAX_RED
-            && self.max_green <= Self::MAX_GREEN
-            && self.max_blue <= Self::MAX_BLUE
+    pub fn draws(&self) -> &[Bag] {
+        &self.draws
     }
 
-    fn power(&self) -> u32 {
-        self.max_red * self.max_green * self.max_blue
+    pub fn is_possible(&self, bag: &Bag) -> bool {
+        self.violation(bag).is_none()
+    }
+
+    /// The first draw (and colour within it) that could not have come from the bag
+    pub fn violation(&self, bag: &Bag) -> Option<Violation> {
+        self.draws.iter().enumerate().find_map(|(i, draw)| {
+            draw.cubes
+                .iter()
+                .find(|(colour, drawn)| *drawn > bag.count(colour))
+                .map(|(colour, drawn)| Violation {
+                    draw: i,
+                    colour: colour.clone(),
+                    drawn: *drawn,
+                    available: bag.count(colour),
+                })
+        })
+    }
+
+    /// The fewest cubes of every colour that make the game possible
+    ///
+    /// Lists the colours of the given bag first, with 0 cubes for those never drawn, followed by any other colour seen.
+    pub fn minimal_bag(&self, bag: &Bag) -> Bag {
+        let mut bag = Bag {
+            cubes: bag.colours().map(|c| (c.to_string(), 0)).collect(),
+        };
+        self.draws
+            .iter()
+            .flat_map(|d| d.cubes.iter())
+            .for_each(|(colour, count)| bag.merge(colour, *count, u32::max));
+
+        bag
     }
 }
 
@@ -114,4 +206,51 @@ mod tests {
 
         assert_eq!(69_629, solve_2(input));
     }
+
+    #[test]
+    fn day_02_bag() {
+        let game =
+            Game::new("Game 3: 8 green, 6 blue, 20 red; 5 blue, 4 red, 13 green; 5 green, 1 red");
+        assert_eq!(3, game.id());
+        assert_eq!(3, game.draws().len());
+        assert_eq!("5 blue, 4 red, 13 green", game.draws()[1].to_string());
+        let bag = Bag::new(BAG);
+        assert_eq!(
+            "20 red, 13 green, 6 blue",
+            game.minimal_bag(&bag).to_string()
+        );
+        assert_eq!(1_560, game.minimal_bag(&bag).power());
+
+        assert_eq!(
+            Some(Violation {
+                draw: 0,
+                colour: "red".to_string(),
+                drawn: 20,
+                available: 12
+            }),
+            game.violation(&bag)
+        );
+        assert!(game.is_possible(&Bag::new("20 red, 13 green, 6 blue")));
+
+        let game = Game::new("Game 7: 2 purple, 1 red; 3 purple, 1 purple");
+        assert_eq!(4, game.draws()[1].count("purple"));
+        assert_eq!(0, game.draws()[1].count("red"));
+        assert_eq!(
+            "1 red, 0 green, 0 blue, 4 purple",
+            game.minimal_bag(&bag).to_string()
+        );
+        assert_eq!(0, game.minimal_bag(&bag).power());
+        assert_eq!(
+            Some(Violation {
+                draw: 0,
+                colour: "purple".to_string(),
+                drawn: 2,
+                available: 0
+            }),
+            game.violation(&bag)
+        );
+
+        // A colour that is never drawn needs no cubes at all
+        assert_eq!(0, solve_2(vec!["Game 1: 3 red, 4 green"]));
+    }
 }
@@ -1,150 +1,203 @@
-use rustc_hash::FxHashSet;
+use std::ops::Not;
 
-const BASE_10: u32 = 10;
+use itertools::Itertools;
+use rustc_hash::FxHashMap;
 
 pub fn solve_1(schematic: Vec<&str>) -> u32 {
-    let schematic: Vec<Vec<char>> = schematic.iter().map(|s| s.chars().collect()).collect();
-
-    extract_numbers(&schematic)
-        .iter()
-        .filter(|n| n.is_part())
+    Schematic::new(schematic)
+        .part_numbers()
         .map(|n| n.value)
         .sum()
 }
 
 pub fn solve_2(schematic: Vec<&str>) -> u32 {
-    let schematic: Vec<_> = schematic.iter().map(|s| s.chars().collect()).collect();
-    let numbers = extract_numbers(&schematic);
-
-    (0..schematic.len())
-        .flat_map(|y| (0..schematic[0].len()).map(move |x| (x, y)))
-        .filter(|(x, y)| schematic[*y][*x] == '*')
-        .map(|(x, y)| {
-            numbers
-                .iter()
-                .filter(|n| n.is_adjacent_to(x as i32, y as i32))
-                .collect::<Vec<_>>()
-        })
-        .filter(|n| n.len() == 2)
-        .map(|n| n[0].value * n[1].value)
+    Schematic::new(schematic)
+        .gears('*', 2)
+        .map(|(_, numbers)| numbers.iter().map(|n| n.value).product::<u32>())
         .sum()
 }
 
-fn extract_numbers(schematic: &Vec<Vec<char>>) -> Vec<Number> {
-    fn push_number_if_reading<'a>(
-        reading_number: &mut bool,
-        numbers: &mut Vec<Number<'a>>,
-        schematic: &'a Vec<Vec<char>>,
-        x_s: i32,
-        x_e: i32,
-        y: i32,
-    ) {
-        if *reading_number {
-            let number = Number::new(x_s, x_e, y, schematic);
-            (*numbers).push(number);
-            *reading_number = false;
-        }
-    }
+/// A number, written on row `y` from column `x_s` up to and including column `x_e`
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Number {
+    pub value: u32,
+    pub x_s: usize,
+    pub x_e: usize,
+    pub y: usize,
+}
 
-    let max_x = schematic[0].len() as i32 - 1;
-    let max_y = schematic.len() as i32 - 1;
+/// Anything that is neither a digit nor a period
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Symbol {
+    pub symbol: char,
+    pub x: usize,
+    pub y: usize,
+}
 
-    let mut numbers: Vec<Number> = Vec::new();
+/// Numbers and symbols are identified by their index in [`Schematic::numbers`] and [`Schematic::symbols`]
+#[derive(Debug)]
+pub struct Schematic {
+    width: usize,
+    height: usize,
+    numbers: Vec<Number>,
+    symbols: Vec<Symbol>,
+    /// Every position covered by a digit, pointing to the number it belongs to
+    number_index: FxHashMap<(usize, usize), usize>,
+    symbol_index: FxHashMap<(usize, usize), usize>,
+}
 
-    let mut reading_number = false;
-    let mut start = 0;
+impl Schematic {
+    pub fn new(schematic: Vec<&str>) -> Schematic {
+        let mut numbers = vec![];
+        let mut symbols = vec![];
 
-    for y in 0..=max_y {
-        push_number_if_reading(
-            &mut reading_number,
-            &mut numbers,
-            schematic,
-            start,
-            max_x,
-            y - 1,
-        );
+        for (y, row) in schematic.iter().enumerate() {
+            let row = row.chars().collect_vec();
+            let mut x = 0;
 
-        for x in 0..=max_x {
-            if schematic[y as usize][x as usize].is_digit(BASE_10) {
-                if !reading_number {
-                    reading_number = true;
-                    start = x;
+            while x < row.len() {
+                if row[x].is_ascii_digit() {
+                    let x_s = x;
+                    let mut value = 0;
+                    while x < row.len() && row[x].is_ascii_digit() {
+                        value = value * 10 + row[x].to_digit(10).unwrap();
+                        x += 1;
+                    }
+                    numbers.push(Number {
+                        value,
+                        x_s,
+                        x_e: x - 1,
+                        y,
+                    });
+                } else {
+                    if row[x] != '.' {
+                        symbols.push(Symbol {
+                            symbol: row[x],
+                            x,
+                            y,
+                        });
+                    }
+                    x += 1;
                 }
-            } else {
-                push_number_if_reading(
-                    &mut reading_number,
-                    &mut numbers,
-                    schematic,
-                    start,
-                    x - 1,
-                    y,
-                );
             }
         }
+
+        let number_index = numbers
+            .iter()
+            .enumerate()
+            .flat_map(|(id, n)| (n.x_s..=n.x_e).map(move |x| ((x, n.y), id)))
+            .collect();
+        let symbol_index = symbols
+            .iter()
+            .enumerate()
+            .map(|(id, s)| ((s.x, s.y), id))
+            .collect();
+
+        Schematic {
+            width: schematic.first().map_or(0, |row| row.chars().count()),
+            height: schematic.len(),
+            numbers,
+            symbols,
+            number_index,
+            symbol_index,
+        }
     }
 
-    push_number_if_reading(
-        &mut reading_number,
-        &mut numbers,
-        schematic,
-        start,
-        max_x,
-        max_y,
-    );
+    pub fn numbers(&self) -> &[Number] {
+        &self.numbers
+    }
 
-    numbers
-}
+    pub fn symbols(&self) -> &[Symbol] {
+        &self.symbols
+    }
 
-struct Number<'a> {
-    value: u32,
-    x_s: i32,
-    x_e: i32,
-    y: i32,
-    schematic: &'a Vec<Vec<char>>,
-}
+    pub fn number_at(&self, x: usize, y: usize) -> Option<&Number> {
+        self.number_index.get(&(x, y)).map(|&id| &self.numbers[id])
+    }
 
-impl<'a> Number<'a> {
-    fn new(x_s: i32, x_e: i32, y: i32, schematic: &'a Vec<Vec<char>>) -> Number<'a> {
-        fn value(x_s: usize, x_e: usize, y: usize, schematic: &[Vec<char>]) -> u32 {
-            let mut value = 0;
+    pub fn symbol_at(&self, x: usize, y: usize) -> Option<&Symbol> {
+        self.symbol_index.get(&(x, y)).map(|&id| &self.symbols[id])
+    }
 
-            for x in x_s..=x_e {
-                value = value * 10 + schematic[y][x].to_digit(BASE_10).unwrap()
-            }
+    /// Numbers with at least one adjacent symbol, in reading order
+    pub fn part_numbers(&self) -> impl Iterator<Item = &Number> + '_ {
+        (0..self.numbers.len())
+            .filter(|&id| self.symbols_adjacent_to(id).is_empty().not())
+            .map(|id| &self.numbers[id])
+    }
 
-            value
-        }
+    /// All numbers adjacent to the given symbol, in reading order
+    pub fn numbers_adjacent_to(&self, symbol: usize) -> Vec<&Number> {
+        let Symbol { x, y, .. } = self.symbols[symbol];
 
-        if x_s > x_e {
-            panic!("The number's end {} is before the start {}", x_e, x_s)
-        }
+        Self::neighbours(x, x, y)
+            .flat_map(|position| self.number_index.get(&position))
+            .copied()
+            .sorted()
+            .dedup()
+            .map(|id| &self.numbers[id])
+            .collect()
+    }
 
-        Number {
-            value: value(x_s as usize, x_e as usize, y as usize, schematic),
-            x_s,
-            x_e,
-            y,
-            schematic,
-        }
+    /// All symbols adjacent to the given number, in reading order
+    pub fn symbols_adjacent_to(&self, number: usize) -> Vec<&Symbol> {
+        let Number { x_s, x_e, y, .. } = self.numbers[number];
+
+        Self::neighbours(x_s, x_e, y)
+            .flat_map(|position| self.symbol_index.get(&position))
+            .sorted()
+            .map(|&id| &self.symbols[id])
+            .collect()
     }
 
-    fn is_part(&self) -> bool {
-        let symbols: FxHashSet<_> = vec!['#', '$', '%', '&', '*', '+', '-', '/', '=', '@']
-            .into_iter()
-            .collect();
-        let empty: Vec<char> = Vec::new();
+    /// Every `symbol` adjacent to exactly `count` numbers, together with those numbers
+    pub fn gears(
+        &self,
+        symbol: char,
+        count: usize,
+    ) -> impl Iterator<Item = (&Symbol, Vec<&Number>)> + '_ {
+        (0..self.symbols.len())
+            .filter(move |&id| self.symbols[id].symbol == symbol)
+            .map(|id| (&self.symbols[id], self.numbers_adjacent_to(id)))
+            .filter(move |(_, numbers)| numbers.len() == count)
+    }
+
+    /// Draws the schematic, with the part numbers in bold green
+    pub fn render(&self) -> String {
+        let part_numbers = (0..self.numbers.len())
+            .filter(|&id| self.symbols_adjacent_to(id).is_empty().not())
+            .collect_vec();
 
-        ((self.y - 1)..=self.y + 1)
-            .flat_map(|y| ((self.x_s - 1)..=self.x_e + 1).map(move |x| (x, y)))
-            .filter(|(x, y)| *x >= 0 && *y >= 0)
-            .map(|(x, y)| (x as usize, y as usize))
-            .filter_map(|(x, y)| self.schematic.get(y).unwrap_or(&empty).get(x))
-            .any(|c| symbols.contains(c))
+        (0..self.height)
+            .map(|y| {
+                let mut row = String::new();
+                let mut x = 0;
+
+                while x < self.width {
+                    if let Some(&id) = self.number_index.get(&(x, y)) {
+                        let number = self.numbers[id];
+                        let value = number.value.to_string();
+                        match part_numbers.binary_search(&id) {
+                            Ok(_) => row.push_str(&format!("\x1b[1;32m{value}\x1b[0m")),
+                            Err(_) => row.push_str(&value),
+                        }
+                        x = number.x_e + 1;
+                    } else {
+                        row.push(self.symbol_at(x, y).map_or('.', |s| s.symbol));
+                        x += 1;
+                    }
+                }
+
+                row
+            })
+            .join("\n")
     }
-    fn is_adjacent_to(&self, x_n: i32, y_n: i32) -> bool {
-        (self.y - 1 == y_n || self.y == y_n || self.y + 1 == y_n)
-            && x_n >= self.x_s - 1
-            && x_n <= self.x_e + 1
+
+    /// Positions surrounding columns `x_s..=x_e` of row `y`, in reading order
+    fn neighbours(x_s: usize, x_e: usize, y: usize) -> impl Iterator<Item = (usize, usize)> {
+        (y.saturating_sub(1)..=y + 1)
+            .flat_map(move |n_y| (x_s.saturating_sub(1)..=x_e + 1).map(move |n_x| (n_x, n_y)))
+            .filter(move |&(n_x, n_y)| n_y != y || n_x < x_s || n_x > x_e)
     }
 }
 
@@ -201,4 +254,67 @@ mod tests {
 
         assert_eq!(84_289_137, solve_2(input));
     }
+
+    #[test]
+    fn day_03_schematic() {
+        let sample = vec![
+            "467..114..",
+            "...*......",
+            "..35..633.",
+            "......#...",
+            "617*......",
+            ".....+.58.",
+            "..592.....",
+            "......755.",
+            "...$.*....",
+            ".664.598..",
+        ];
+        let schematic = Schematic::new(sample);
+
+        assert_eq!(10, schematic.numbers().len());
+        assert_eq!(6, schematic.symbols().len());
+        assert_eq!(Some(633), schematic.number_at(7, 2).map(|n| n.value));
+        assert_eq!(Some('#'), schematic.symbol_at(6, 3).map(|s| s.symbol));
+        assert_eq!(None, schematic.number_at(0, 1));
+
+        assert_eq!(
+            vec![467, 35],
+            schematic
+                .numbers_adjacent_to(0)
+                .iter()
+                .map(|n| n.value)
+                .collect_vec()
+        );
+        assert_eq!(
+            vec![Symbol {
+                symbol: '$',
+                x: 3,
+                y: 8
+            }],
+            schematic
+                .symbols_adjacent_to(8)
+                .into_iter()
+                .copied()
+                .collect_vec()
+        );
+        assert!(schematic.symbols_adjacent_to(1).is_empty());
+
+        assert_eq!(
+            vec![(3, 4, vec![617])],
+            schematic
+                .gears('*', 1)
+                .map(|(s, n)| (s.x, s.y, n.iter().map(|n| n.value).collect_vec()))
+                .collect_vec()
+        );
+        assert_eq!(1, schematic.gears('$', 1).count());
+        assert_eq!(0, schematic.gears('#', 2).count());
+
+        let render = schematic.render();
+        assert_eq!(10, render.lines().count());
+        assert_eq!(
+            "\x1b[1;32m467\x1b[0m..114..",
+            render.lines().next().unwrap()
+        );
+        assert_eq!("...$.*....", render.lines().nth(8).unwrap());
+    }
 }
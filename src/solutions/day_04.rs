@@ -1,5 +1,9 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::fmt::{Display, Formatter};
 use std::str::FromStr;
 
+use itertools::Itertools;
 use regex::Regex;
 use rustc_hash::FxHashSet;
 
@@ -8,20 +12,157 @@ pub fn solve_1(cards: Vec<&str>) -> u32 {
 }
 
 pub fn solve_2(cards: Vec<&str>) -> u32 {
+    cascade(cards, &CopyRules::standard()).unwrap().total() as u32
+}
+
+/// Plays out all copies won by the given rules
+///
+/// Fails when some card would (indirectly) keep winning copies of itself, as the pile would then never stop growing.
+pub fn cascade(cards: Vec<&str>, rules: &CopyRules) -> Result<Cascade, String> {
     let cards = parse_cards(&cards);
-    let mut pile = vec![1; cards.len() + 1];
-    pile[0] = 0; // Sneaky way to 1-index the array
+    let windows = cards
+        .iter()
+        .enumerate()
+        .map(|(position, card)| rules.window(position, card.nr_matches() as usize, cards.len()))
+        .collect_vec();
+
+    // Kahn's algorithm, taking the earliest card first when there is a choice
+    let mut nr_spawners = vec![0; cards.len()];
+    windows
+        .iter()
+        .flatten()
+        .for_each(|&to| nr_spawners[to] += 1);
+    let mut ready: BinaryHeap<_> = (0..cards.len())
+        .filter(|&position| nr_spawners[position] == 0)
+        .map(Reverse)
+        .collect();
+
+    let mut copies = vec![1; cards.len()];
+    let mut spawns = vec![];
+    let mut nr_played = 0;
 
-    for card in cards {
-        let nr_matches = card.nr_matches();
-        let nr_copies = pile[card.card_id as usize];
+    while let Some(Reverse(from)) = ready.pop() {
+        nr_played += 1;
 
-        for card_id in (&card.card_id + 1)..(&card.card_id + 1 + nr_matches) {
-            pile[card_id as usize] += nr_copies;
+        for &to in &windows[from] {
+            let won = copies[from] * rules.multiplier;
+            copies[to] += won;
+            spawns.push(Spawn {
+                from: cards[from].card_id,
+                to: cards[to].card_id,
+                copies: won,
+            });
+
+            nr_spawners[to] -= 1;
+            if nr_spawners[to] == 0 {
+                ready.push(Reverse(to));
+            }
         }
     }
 
-    pile.iter().sum()
+    if nr_played < cards.len() {
+        return Err("Cards keep winning copies of themselves, the cascade never ends".to_string());
+    }
+
+    let reports = cards
+        .iter()
+        .zip(copies)
+        .map(|(card, copies)| CardReport {
+            card_id: card.card_id,
+            original: 1,
+            won: copies - 1,
+        })
+        .collect();
+
+    Ok(Cascade { reports, spawns })
+}
+
+/// The rules by which matching numbers win copies of later cards.
+#[derive(Debug, Clone)]
+pub struct CopyRules {
+    /// How many cards after the winning card the copies start.
+    pub offset: usize,
+    /// How many copies every instance of the winning card wins per card.
+    pub multiplier: u64,
+    pub overflow: Overflow,
+}
+
+/// What happens to copies won past the last card.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Overflow {
+    /// They are lost, as cards will never make you copy a card past the end of the table.
+    Cap,
+    /// They continue from the first card.
+    Wrap,
+}
+
+impl CopyRules {
+    pub fn standard() -> CopyRules {
+        CopyRules {
+            offset: 1,
+            multiplier: 1,
+            overflow: Overflow::Cap,
+        }
+    }
+
+    /// Positions of the cards won by the card at `position`
+    fn window(&self, position: usize, nr_matches: usize, nr_cards: usize) -> Vec<usize> {
+        let window = (position + self.offset)..(position + self.offset + nr_matches);
+
+        match self.overflow {
+            Overflow::Cap => window.take_while(|&to| to < nr_cards).collect(),
+            Overflow::Wrap => window.map(|to| to % nr_cards).collect(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Cascade {
+    reports: Vec<CardReport>,
+    spawns: Vec<Spawn>,
+}
+
+impl Cascade {
+    /// How many instances of every card end up on the pile, in the order of the cards
+    pub fn reports(&self) -> &[CardReport] {
+        &self.reports
+    }
+
+    /// Which card won copies of which card, in the order they were won
+    pub fn spawns(&self) -> &[Spawn] {
+        &self.spawns
+    }
+
+    pub fn total(&self) -> u64 {
+        self.reports.iter().map(|r| r.original + r.won).sum()
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct CardReport {
+    pub card_id: u32,
+    pub original: u64,
+    pub won: u64,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Spawn {
+    pub from: u32,
+    pub to: u32,
+    pub copies: u64,
+}
+
+impl Display for Spawn {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Card {} wins {} {} of card {}",
+            self.from,
+            self.copies,
+            if self.copies == 1 { "copy" } else { "copies" },
+            self.to
+        )
+    }
 }
 
 fn parse_cards(cards: &[&str]) -> Vec<Card> {
@@ -129,4 +270,81 @@ mod tests {
 
         assert_eq!(12_263_631, solve_2(input));
     }
+
+    #[test]
+    fn day_04_cascade() {
+        let sample = vec![
+            "Card 1: 41 48 83 86 17 | 83 86  6 31 17  9 48 53",
+            "Card 2: 13 32 20 16 61 | 61 30 68 82 17 32 24 19",
+            "Card 3:  1 21 53 59 44 | 69 82 63 72 16 21 14  1",
+            "Card 4: 41 92 73 84 69 | 59 84 76 51 58  5 54 83",
+            "Card 5: 87 83 26 28 32 | 88 30 70 12 93 22 82 36",
+            "Card 6: 31 18 13 56 72 | 74 77 10 23 35 67 36 11",
+        ];
+
+        let standard = cascade(sample.clone(), &CopyRules::standard()).unwrap();
+        assert_eq!(
+            vec![0, 1, 3, 7, 13, 0],
+            standard.reports().iter().map(|r| r.won).collect_vec()
+        );
+        assert_eq!(
+            vec![
+                "Card 1 wins 1 copy of card 2",
+                "Card 1 wins 1 copy of card 3",
+                "Card 1 wins 1 copy of card 4",
+                "Card 1 wins 1 copy of card 5",
+                "Card 2 wins 2 copies of card 3",
+                "Card 2 wins 2 copies of card 4",
+            ],
+            standard.spawns()[..6]
+                .iter()
+                .map(|s| s.to_string())
+                .collect_vec()
+        );
+
+        let doubled = CopyRules {
+            multiplier: 2,
+            ..CopyRules::standard()
+        };
+        assert_eq!(116, cascade(sample.clone(), &doubled).unwrap().total());
+
+        let skip_one = CopyRules {
+            offset: 2,
+            ..CopyRules::standard()
+        };
+        assert_eq!(19, cascade(sample, &skip_one).unwrap().total());
+    }
+
+    #[test]
+    fn day_04_wrap_around() {
+        let cards = vec![
+            "Card 1: 1 2 | 3 4",
+            "Card 2: 1 2 | 3 4",
+            "Card 3: 1 2 | 1 4",
+        ];
+        let wrap = CopyRules {
+            overflow: Overflow::Wrap,
+            ..CopyRules::standard()
+        };
+
+        assert_eq!(
+            3,
+            cascade(cards.clone(), &CopyRules::standard())
+                .unwrap()
+                .total()
+        );
+        let wrapped = cascade(cards, &wrap).unwrap();
+        assert_eq!(4, wrapped.total());
+        assert_eq!(
+            CardReport {
+                card_id: 1,
+                original: 1,
+                won: 1
+            },
+            wrapped.reports()[0]
+        );
+
+        let cycle = vec!["Card 1: 1 2 | 1 4", "Card 2: 1 2 | 1 4"];
+        assert!(cascade(cycle, &wrap).is_err());
+    }
 }
@@ -1,8 +1,7 @@
 use std::ops::Range;
 use std::str::FromStr;
 
-use itertools::Itertools;
-use rustc_hash::FxHashSet;
+use crate::util::interval::{IntervalMap, IntervalSet};
 
 pub fn solve_1(almanac: &str) -> u64 {
     solve(almanac, &Almanac::seeds_as_single_ranges)
@@ -12,37 +11,38 @@ pub fn solve_2(almanac: &str) -> u64 {
     solve(almanac, &Almanac::seeds_as_ranges)
 }
 
-fn solve(almanac: &str, seeds_to_ranges: &dyn Fn(&Almanac) -> FxHashSet<Range<u64>>) -> u64 {
+fn solve(almanac: &str, seeds_to_ranges: &dyn Fn(&Almanac) -> IntervalSet) -> u64 {
     let almanac = Almanac::new(almanac);
-    let mut ranges = seeds_to_ranges(&almanac);
 
-    for category in almanac.categories {
-        ranges = category.convert(&ranges)
-    }
-
-    ranges.iter().map(|r| r.start).min().unwrap()
+    almanac
+        .seed_to_location()
+        .image(&seeds_to_ranges(&almanac))
+        .min()
+        .unwrap()
 }
 
 #[derive(Debug)]
-struct Almanac {
+pub struct Almanac {
     seeds: Vec<u64>,
     categories: Vec<Category>,
 }
 
 #[derive(Debug)]
 struct Category {
-    _name: String,
-    mappings: Vec<Mapping>,
+    name: String,
+    map: IntervalMap,
 }
 
-#[derive(Debug)]
-struct Mapping {
-    source: Range<u64>,
-    destination: Range<u64>,
+/// The seed range (as listed in the almanac) holding the seed with the lowest location.
+#[derive(Debug, Eq, PartialEq)]
+pub struct LowestLocation {
+    pub seed_range: Range<u64>,
+    pub seed: u64,
+    pub location: u64,
 }
 
 impl Almanac {
-    fn new(almanac: &str) -> Almanac {
+    pub fn new(almanac: &str) -> Almanac {
         let split: Vec<&_> = almanac.split("\n\n").collect();
 
         let seeds: Vec<_> = split[0]
@@ -55,111 +55,94 @@ impl Almanac {
         Almanac { seeds, categories }
     }
 
-    fn seeds_as_single_ranges(&self) -> FxHashSet<Range<u64>> {
-        self.seeds.iter().map(|s| *s..*s + 1).collect()
+    /// All categories merged into a single map, going from seed straight to location.
+    pub fn seed_to_location(&self) -> IntervalMap {
+        self.categories
+            .iter()
+            .fold(IntervalMap::identity(), |map, c| map.compose(&c.map))
     }
 
-    fn seeds_as_ranges(&self) -> FxHashSet<Range<u64>> {
-        self.seeds
-            .chunks(2)
-            .map(|c| {
-                let (seed, range) = (c[0], c[1]);
-                seed..seed + range
-            })
-            .collect()
+    /// Follows a seed through every category, yielding the value at each stage, starting with the seed itself.
+    pub fn trace(&self, seed: u64) -> Vec<(&str, u64)> {
+        let mut value = seed;
+        let mut trace = vec![("seed", seed)];
+
+        for category in &self.categories {
+            value = category.map.get(value);
+            trace.push((category.destination(), value));
+        }
+
+        trace
     }
-}
 
-impl Category {
-    fn new(category: &str) -> Category {
-        let split: Vec<_> = category.split('\n').collect();
+    /// All seeds ending up at the location, whether they are listed in the almanac or not.
+    pub fn seeds_for_location(&self, location: u64) -> IntervalSet {
+        self.seed_to_location()
+            .preimage(&IntervalSet::from(location..location + 1))
+    }
 
-        let _name = split[0].trim_end_matches(" map:").to_string();
-        let mappings: Vec<_> = split
-            .iter()
-            .skip(1)
-            .map(|&s| Mapping::new(s))
-            .sorted_by_key(|m| m.source.start)
-            .collect();
+    /// Finds the lowest location when the seeds are read as ranges, along with the range and seed leading to it.
+    pub fn lowest_location(&self) -> Option<LowestLocation> {
+        let seed_to_location = self.seed_to_location();
+
+        self.seed_ranges()
+            .filter_map(|seed_range| {
+                let location = seed_to_location
+                    .image(&IntervalSet::from(seed_range.clone()))
+                    .min()?;
+                let seed = seed_to_location
+                    .preimage(&IntervalSet::from(location..location + 1))
+                    .intersection(&IntervalSet::from(seed_range.clone()))
+                    .min()?;
+
+                Some(LowestLocation {
+                    seed_range,
+                    seed,
+                    location,
+                })
+            })
+            .min_by_key(|l| l.location)
+    }
 
-        Category { _name, mappings }
+    pub fn seeds_as_single_ranges(&self) -> IntervalSet {
+        self.seeds.iter().map(|s| *s..*s + 1).collect()
     }
 
-    fn convert(&self, ranges: &FxHashSet<Range<u64>>) -> FxHashSet<Range<u64>> {
-        ranges
-            .iter()
-            .flat_map(|r| Self::convert_single(self, r))
-            .collect()
+    pub fn seeds_as_ranges(&self) -> IntervalSet {
+        self.seed_ranges().collect()
     }
 
-    fn convert_single(&self, range: &Range<u64>) -> FxHashSet<Range<u64>> {
-        let mut converted_ranges = FxHashSet::default();
-        let mut current = range.start;
+    fn seed_ranges(&self) -> impl Iterator<Item = Range<u64>> + '_ {
+        self.seeds.chunks(2).map(|c| {
+            let (seed, range) = (c[0], c[1]);
+            seed..seed + range
+        })
+    }
+}
 
-        // First case to account for is our range ending before the first mapping's start
-        let first_mapping_start = self.mappings.first().unwrap().source.start;
-        if range.end <= first_mapping_start {
-            converted_ranges.insert(range.start..range.end);
-        }
+impl Category {
+    fn new(category: &str) -> Category {
+        let split: Vec<_> = category.split('\n').collect();
 
-        for mapping in &self.mappings {
-            let s_start = mapping.source.start;
-            let s_end = mapping.source.end;
-            let d_start = mapping.destination.start;
-
-            // We're done once we start seeing mapping ranges beyond our own, so stop
-            if range.end <= s_start {
-                break;
-            }
-
-            // We're not interested in this mapping if its values are before our own range, so skip
-            if current >= s_end {
-                continue;
-            }
-
-            // This means (at least the start of) our range is unmapped and should be recorded
-            // A check is needed to see which comes first, our range's end, or the mapping
-            if current < s_start && range.end > s_start {
-                converted_ranges.insert(current..s_start);
-                current = s_start
-            } else if current < s_start && range.end <= s_start {
-                converted_ranges.insert(current..range.end);
-                break; // We fully mapped out our range
-            }
-
-            // At this point we are sure at least some part of our current range has a mapping
-            if range.end <= s_end {
-                converted_ranges
-                    .insert((current - s_start + d_start)..(range.end - s_start + d_start));
-                break; // We fully mapped out our range
-            } else {
-                converted_ranges.insert((current - s_start + d_start)..(s_end - s_start + d_start));
-                current = s_end;
-            }
-        }
+        let name = split[0].trim_end_matches(" map:").to_string();
+        let map = IntervalMap::new(split.iter().skip(1).map(|&s| Self::parse_mapping(s)));
 
-        // Final case to account for is our range going beyond the last mapping's end
-        let last_mapping_end = self.mappings.last().unwrap().source.end;
-        if range.end > last_mapping_end {
-            converted_ranges.insert(current..range.end);
-        }
+        Category { name, map }
+    }
 
-        converted_ranges
+    /// The name of what this category converts into, e.g. "soil" for "seed-to-soil".
+    fn destination(&self) -> &str {
+        self.name.split("-to-").last().unwrap()
     }
-}
 
-impl Mapping {
-    fn new(mapping: &str) -> Mapping {
+    fn parse_mapping(mapping: &str) -> (Range<u64>, u64) {
         let split: Vec<_> = mapping.split(' ').flat_map(u64::from_str).collect();
 
         let destination_start = split[0];
         let source_start = split[1];
         let range_length = split[2];
 
-        Mapping {
-            source: source_start..source_start + range_length,
-            destination: destination_start..destination_start + range_length,
-        }
+        (source_start..source_start + range_length, destination_start)
     }
 }
 
@@ -252,6 +235,81 @@ mod tests {
         assert_eq!(46, solve_2(sample));
     }
 
+    #[test]
+    fn day_05_seed_to_location() {
+        let sample = "seeds: 79 14 55 13\n\
+            \n\
+            seed-to-soil map:\n\
+            50 98 2\n\
+            52 50 48\n\
+            \n\
+            soil-to-fertilizer map:\n\
+            0 15 37\n\
+            37 52 2\n\
+            39 0 15\n\
+            \n\
+            fertilizer-to-water map:\n\
+            49 53 8\n\
+            0 11 42\n\
+            42 0 7\n\
+            57 7 4\n\
+            \n\
+            water-to-light map:\n\
+            88 18 7\n\
+            18 25 70\n\
+            \n\
+            light-to-temperature map:\n\
+            45 77 23\n\
+            81 45 19\n\
+            68 64 13\n\
+            \n\
+            temperature-to-humidity map:\n\
+            0 69 1\n\
+            1 0 69\n\
+            \n\
+            humidity-to-location map:\n\
+            60 56 37\n\
+            56 93 4";
+        let almanac = Almanac::new(sample);
+        let seed_to_location = almanac.seed_to_location();
+
+        assert_eq!(82, seed_to_location.get(79));
+        assert_eq!(43, seed_to_location.get(14));
+        assert_eq!(86, seed_to_location.get(55));
+        assert_eq!(35, seed_to_location.get(13));
+
+        assert_eq!(
+            vec![
+                ("seed", 79),
+                ("soil", 81),
+                ("fertilizer", 81),
+                ("water", 81),
+                ("light", 74),
+                ("temperature", 78),
+                ("humidity", 78),
+                ("location", 82)
+            ],
+            almanac.trace(79)
+        );
+
+        assert_eq!(IntervalSet::from(82..83), almanac.seeds_for_location(46));
+        assert_eq!(
+            Some(LowestLocation {
+                seed_range: 79..93,
+                seed: 82,
+                location: 46
+            }),
+            almanac.lowest_location()
+        );
+
+        let location_to_seed = seed_to_location.invert().unwrap();
+        assert_eq!(82, location_to_seed.get(46));
+        assert_eq!(
+            seed_to_location.to_string().lines().count(),
+            seed_to_location.mappings().len()
+        );
+    }
+
     #[test]
     fn day_05_part_02_solution() {
         let input = include_str!("../../inputs/day_05.txt");
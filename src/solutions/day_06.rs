@@ -1,19 +1,25 @@
 use std::str::FromStr;
 
+use num_bigint::BigUint;
+use num_traits::Zero;
 use regex::Regex;
 
 pub fn solve_1(races: Vec<&str>) -> u64 {
     parse_races_bad_kerning(races)
         .iter()
-        .map(|r| r.nr_wins_closed_form())
+        .map(|r| r.nr_wins())
         .product()
 }
 
 pub fn solve_2(races: Vec<&str>) -> u64 {
-    parse_races_good_kerning(races)
-        .iter()
-        .map(|r| r.nr_wins_closed_form())
-        .product()
+    u64::try_from(nr_wins_good_kerning(races)).unwrap()
+}
+
+/// Counts the ways to win the single race formed by ignoring the spaces between the numbers
+///
+/// The concatenated numbers can grow arbitrarily large, so they are handled as big integers.
+pub fn nr_wins_good_kerning(races: Vec<&str>) -> BigUint {
+    parse_race_good_kerning(races).nr_wins()
 }
 
 fn parse_races_bad_kerning(timings: Vec<&str>) -> Vec<Race> {
@@ -37,16 +43,16 @@ fn parse_races_bad_kerning(timings: Vec<&str>) -> Vec<Race> {
         .collect()
 }
 
-fn parse_races_good_kerning(timings: Vec<&str>) -> Vec<Race> {
+fn parse_race_good_kerning(timings: Vec<&str>) -> LongRace {
     let re = Regex::new(r"\d+").unwrap();
 
     let time: String = re.find_iter(timings[0]).map(|m| m.as_str()).collect();
     let distance: String = re.find_iter(timings[1]).map(|m| m.as_str()).collect();
 
-    vec![Race {
-        time: u64::from_str(&time).unwrap(),
-        distance: u64::from_str(&distance).unwrap(),
-    }]
+    LongRace {
+        time: BigUint::from_str(&time).unwrap(),
+        distance: BigUint::from_str(&distance).unwrap(),
+    }
 }
 
 #[derive(Debug)]
@@ -64,30 +70,82 @@ impl Race {
     // => x * (time - x) > dist
     // => -x^2 + x * time - dist > 0
     //
-    // This gives us the following parameters to solve for the roots
-    // => a = -1
-    // => b = time
-    // => c = -dist
+    // The roots of the equation are found at
+    // => (time +- sqrt(time^2 - 4 * dist)) / 2
     //
-    // Finally the roots can be found at
-    // => (-b +- sqrt(b * b - 4 * a * c)) / 2 * a
+    // All presses strictly between both roots win, and they are symmetric around time / 2
+    // Hence it suffices to find the shortest winning press (lo), after which the answer is
+    // the number of elements in lo..=(time - lo)
     //
-    // The solution then becomes counting the number of elements in the range formed by the roots
-    // Special care is taken in both rounding the roots up to align the range AND adding a tiny
-    // bit to the first root, to fix the edge case of having a fully integer route
-    // The reason for it is because we solve roots for "... > 0" and not "... = 0"
-    fn nr_wins_closed_form(&self) -> u64 {
-        let a = -1.0;
-        let b = self.time as f64;
-        let c = -(self.distance as f64);
+    // Everything is computed in u128 (time^2 can not overflow it) with an integer square root
+    // The root is only an estimate of the boundary, which is then nudged until it is exact
+    fn nr_wins(&self) -> u64 {
+        let time = self.time as u128;
+        let distance = self.distance as u128;
+        let wins = |x: u128| x * (time - x) > distance;
+
+        if !wins(time / 2) {
+            return 0;
+        }
+
+        let mut lo = (time - isqrt(time * time - 4 * distance).min(time)) / 2;
+        while !wins(lo) {
+            lo += 1;
+        }
+        while lo > 0 && wins(lo - 1) {
+            lo -= 1;
+        }
+
+        (time - 2 * lo + 1) as u64
+    }
+}
 
-        let d = b * b - 4.0 * a * c;
+/// Same as [`Race`], but without any bound on how large the numbers can get
+#[derive(Debug)]
+struct LongRace {
+    time: BigUint,
+    distance: BigUint,
+}
+
+impl LongRace {
+    fn nr_wins(&self) -> BigUint {
+        let time = &self.time;
+        let wins = |x: &BigUint| x * (time - x) > self.distance;
+
+        let half = time / 2u32;
+        if !wins(&half) {
+            return BigUint::zero();
+        }
+
+        // The maximum is reached at half time, so the discriminant is positive
+        let discriminant = time * time - &self.distance * 4u32;
+        let mut lo = (time - discriminant.sqrt().min(time.clone())) / 2u32;
+        while !wins(&lo) {
+            lo += 1u32;
+        }
+        while !lo.is_zero() && wins(&(&lo - 1u32)) {
+            lo -= 1u32;
+        }
+
+        time + 1u32 - lo * 2u32
+    }
+}
 
-        let root_1 = (-b + d.sqrt()) / -2.0 + 0.0000001;
-        let root_2 = (-b - d.sqrt()) / -2.0;
+/// Integer square root, rounded down
+fn isqrt(n: u128) -> u128 {
+    let mut root = (n as f64).sqrt() as u128;
 
-        root_2.ceil() as u64 - root_1.ceil() as u64
+    while root.checked_mul(root).is_none_or(|square| square > n) {
+        root -= 1;
+    }
+    while (root + 1)
+        .checked_mul(root + 1)
+        .is_some_and(|square| square <= n)
+    {
+        root += 1;
     }
+
+    root
 }
 
 #[cfg(test)]
@@ -121,4 +179,52 @@ mod tests {
 
         assert_eq!(46_561_107, solve_2(input));
     }
+
+    fn nr_wins_brute_force(time: u64, distance: u64) -> u64 {
+        (0..=time).filter(|x| x * (time - x) > distance).count() as u64
+    }
+
+    #[test]
+    fn day_06_exact_roots() {
+        for time in 0..100 {
+            for distance in 0..(time * time / 4 + 2) {
+                let race = Race { time, distance };
+                let long_race = LongRace {
+                    time: BigUint::from(time),
+                    distance: BigUint::from(distance),
+                };
+                let expected = nr_wins_brute_force(time, distance);
+
+                assert_eq!(expected, race.nr_wins(), "{:?}", race);
+                assert_eq!(BigUint::from(expected), long_race.nr_wins(), "{:?}", race);
+            }
+        }
+    }
+
+    #[test]
+    fn day_06_large_races() {
+        // Beyond 2^53 floating point square roots are no longer exact
+        let time = u64::MAX;
+        let race = Race { time, distance: 0 };
+        assert_eq!(u64::MAX - 1, race.nr_wins());
+
+        // Pressing for 2^32 out of 2^33 reaches 2^64, one step further either way reaches 2^64 - 1
+        let race = Race {
+            time: 1 << 33,
+            distance: u64::MAX - 1,
+        };
+        assert_eq!(3, race.nr_wins());
+        let race = Race {
+            time: 1 << 33,
+            distance: u64::MAX,
+        };
+        assert_eq!(1, race.nr_wins());
+
+        let sample = vec![
+            "Time:      123456789  123456789  123456789",
+            "Distance:  1  2  3",
+        ];
+        let time = BigUint::from_str("123456789123456789123456789").unwrap();
+        assert_eq!(time - 1u32, nr_wins_good_kerning(sample));
+    }
 }
@@ -2,14 +2,61 @@ use std::str::FromStr;
 
 use itertools::Itertools;
 
-const BASE_10: u32 = 10;
-
 pub fn solve_1(hands: Vec<&str>) -> u32 {
-    Game::new(hands, false).score()
+    Game::new(hands, &CardRules::standard()).score()
 }
 
 pub fn solve_2(hands: Vec<&str>) -> u32 {
-    Game::new(hands, true).score()
+    Game::new(hands, &CardRules::jokers()).score()
+}
+
+/// Total winnings of all hands, when played by the given rules.
+pub fn score(hands: Vec<&str>, rules: &CardRules) -> u32 {
+    Game::new(hands, rules).score()
+}
+
+/// Describes every hand's type and rank, from the weakest to the strongest hand.
+pub fn explain(hands: Vec<&str>, rules: &CardRules) -> Vec<String> {
+    Game::new(hands, rules).explain()
+}
+
+/// The rules by which hands are typed and ranked.
+#[derive(Debug, Clone)]
+pub struct CardRules {
+    /// The number of cards in every hand.
+    pub hand_size: usize,
+    /// All cards, from the weakest to the strongest.
+    pub rank_order: String,
+    /// Cards acting as whatever card makes the strongest type, but being weaker than any other card on their own.
+    pub wildcards: Vec<char>,
+    pub tie_break: TieBreak,
+}
+
+/// How hands of the same type are ordered.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum TieBreak {
+    /// Compare the cards one by one, in the order they were dealt.
+    InOrder,
+    /// Compare the cards one by one, from the strongest to the weakest card in the hand.
+    HighestFirst,
+}
+
+impl CardRules {
+    pub fn standard() -> CardRules {
+        CardRules {
+            hand_size: 5,
+            rank_order: "23456789TJQKA".to_string(),
+            wildcards: vec![],
+            tie_break: TieBreak::InOrder,
+        }
+    }
+
+    pub fn jokers() -> CardRules {
+        CardRules {
+            wildcards: vec!['J'],
+            ..Self::standard()
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -19,27 +66,29 @@ struct Game<'a> {
 
 #[derive(Debug, Ord, PartialOrd, Eq, PartialEq)]
 struct Hand<'a> {
-    h_type: Type<'a>,
+    h_type: Type,
     cards: Vec<Rank>,
     bid: u32,
+    name: &'a str,
 }
 
 #[derive(Debug, Ord, PartialOrd, Eq, PartialEq)]
-struct Type<'a> {
-    value: u32,
-    _name: &'a str,
+struct Type {
+    /// Sizes of the groups of equal cards, from the largest to the smallest group
+    value: Vec<usize>,
+    name: String,
 }
 
 #[derive(Debug, Ord, PartialOrd, Eq, PartialEq)]
 struct Rank {
-    value: u32,
+    value: usize,
     _name: char,
 }
 
 impl<'a> Game<'a> {
-    fn new(hands: Vec<&str>, joker: bool) -> Game {
+    fn new(hands: Vec<&'a str>, rules: &CardRules) -> Game<'a> {
         Game {
-            hands: hands.iter().map(|h| Hand::new(h, joker)).collect(),
+            hands: hands.iter().map(|h| Hand::new(h, rules)).collect(),
         }
     }
 
@@ -52,80 +101,96 @@ impl<'a> Game<'a> {
             .map(|(rank, bid)| (rank as u32 + 1) * bid)
             .sum()
     }
+
+    fn explain(&self) -> Vec<String> {
+        self.hands
+            .iter()
+            .sorted()
+            .enumerate()
+            .map(|(rank, h)| format!("{} {}: {}, rank {}", h.name, h.bid, h.h_type.name, rank + 1))
+            .collect()
+    }
 }
 
 impl<'a> Hand<'a> {
-    fn new(line: &str, joker: bool) -> Hand {
+    fn new(line: &'a str, rules: &CardRules) -> Hand<'a> {
         let line: Vec<_> = line.split(' ').collect();
+        let name = line[0];
+
+        if name.chars().count() != rules.hand_size {
+            panic!("Invalid hand size: {name}");
+        }
+
+        let mut cards = name.chars().map(|r| Rank::new(r, rules)).collect_vec();
+        if rules.tie_break == TieBreak::HighestFirst {
+            cards.sort_by(|a, b| b.cmp(a));
+        }
 
         Hand {
-            h_type: Type::new(line[0], joker),
-            cards: line[0].chars().map(|r| Rank::new(r, joker)).collect(),
+            h_type: Type::new(name, rules),
+            cards,
             bid: u32::from_str(line[1]).unwrap(),
+            name,
         }
     }
 }
 
-#[rustfmt::skip]
-impl<'a> Type<'a> {
-    fn new(hand: &'a str, joker: bool) -> Type {
-        let hand = Self::resolve_jokers(hand, joker);
-        let counts = hand.chars().counts();
-
-        match counts.len() {
-            1 => Type { value: 6, _name: "Five of a kind" },
-            2 => {
-                if counts.values().any(|&c| c == 4) {
-                    Type { value: 5, _name: "Four of a kind" }
-                } else {
-                    Type { value: 4, _name: "Full house" }
-                }
-            }
-            3 => {
-                if counts.values().any(|&c| c == 3) {
-                    Type { value: 3, _name: "Three of a kind" }
-                } else {
-                    Type { value: 2, _name: "Two pair" }
-                }
+impl Type {
+    fn new(hand: &str, rules: &CardRules) -> Type {
+        let value = Self::resolve_wildcards(hand, rules);
+
+        let name = match (value[0], value.get(1).copied().unwrap_or(0)) {
+            (1, _) => "High card".to_string(),
+            (2, 2) => match value.iter().filter(|&&c| c == 2).count() {
+                2 => "Two pair".to_string(),
+                pairs => format!("{pairs} pairs"),
+            },
+            (2, _) => "One pair".to_string(),
+            (3, 3) => {
+                let triples = value.iter().filter(|&&c| c == 3).count();
+                format!("{triples} triples")
             }
-            4 => Type { value: 1, _name: "One Pair" },
-            5 => Type { value: 0, _name: "High card" },
-            _ => panic!("Invalid hand: {hand}"),
-        }
+            (3, 2) => "Full house".to_string(),
+            (3, _) => "Three of a kind".to_string(),
+            (4, _) => "Four of a kind".to_string(),
+            (5, _) => "Five of a kind".to_string(),
+            (n, _) => format!("{n} of a kind"),
+        };
+
+        Type { value, name }
     }
 
-    fn resolve_jokers(hand: &'a str, joker: bool) -> String {
-        let hand_without_jokers = hand.replace('J', "");
-        let most_common_card = hand_without_jokers
+    /// Groups the cards by kind, then adds all wildcards to the largest group.
+    fn resolve_wildcards(hand: &str, rules: &CardRules) -> Vec<usize> {
+        let nr_wildcards = hand.chars().filter(|c| rules.wildcards.contains(c)).count();
+        let mut groups = hand
             .chars()
+            .filter(|c| !rules.wildcards.contains(c))
             .counts()
-            .iter()
-            .max_by_key(|e| e.1)
-            .map(|e| *e.0)
-            .unwrap_or('A');
+            .into_values()
+            .sorted_by(|a, b| b.cmp(a))
+            .collect_vec();
 
-        if joker {
-            hand.replace('J', most_common_card.to_string().as_str())
-        } else {
-            hand.to_string()
+        match groups.first_mut() {
+            Some(largest) => *largest += nr_wildcards,
+            None => groups.push(nr_wildcards),
         }
+
+        groups
     }
 }
 
 impl Rank {
-    fn new(_name: char, joker: bool) -> Rank {
-        let value = match _name {
-            'A' => 14,
-            'K' => 13,
-            'Q' => 12,
-            'J' => match joker {
-                true => 1,
-                false => 11,
-            },
-            'T' => 10,
-            '2'..='9' => _name.to_digit(BASE_10).unwrap(),
-            _ => panic!("Unsupported rank, cannot parse: {_name}"),
+    fn new(_name: char, rules: &CardRules) -> Rank {
+        let value = if rules.wildcards.contains(&_name) {
+            0
+        } else {
+            match rules.rank_order.chars().position(|c| c == _name) {
+                Some(position) => position + 1,
+                None => panic!("Unsupported rank, cannot parse: {_name}"),
+            }
         };
+
         Rank { value, _name }
     }
 }
@@ -167,6 +232,76 @@ mod tests {
         assert_eq!(5_905, solve_2(sample));
     }
 
+    #[test]
+    fn day_07_explain() {
+        let sample = vec![
+            "32T3K 765",
+            "T55J5 684",
+            "KK677 28",
+            "KTJJT 220",
+            "QQQJA 483",
+        ];
+
+        assert_eq!(
+            vec![
+                "32T3K 765: One pair, rank 1",
+                "KK677 28: Two pair, rank 2",
+                "T55J5 684: Four of a kind, rank 3",
+                "QQQJA 483: Four of a kind, rank 4",
+                "KTJJT 220: Four of a kind, rank 5",
+            ],
+            explain(sample, &CardRules::jokers())
+        );
+    }
+
+    #[test]
+    fn day_07_house_rules() {
+        let sample = vec![
+            "32T3K 765",
+            "T55J5 684",
+            "KK677 28",
+            "KTJJT 220",
+            "QQQJA 483",
+        ];
+
+        // Comparing the strongest cards first lets the ace win, instead of the leading three
+        let high_cards = vec!["2A345 1", "3KQJ9 2"];
+        assert_eq!(5, score(high_cards.clone(), &CardRules::standard()));
+        let rules = CardRules {
+            tie_break: TieBreak::HighestFirst,
+            ..CardRules::standard()
+        };
+        assert_eq!(4, score(high_cards, &rules));
+
+        // With both jokers and twos wild, "32T3K" becomes three of a kind
+        let rules = CardRules {
+            wildcards: vec!['J', '2'],
+            ..CardRules::standard()
+        };
+        assert_eq!(
+            "32T3K 765: Three of a kind, rank 2",
+            explain(sample, &rules)[1]
+        );
+
+        // Hands of any size, with their own set of cards
+        let sample = vec!["AAAAAA 1", "ABABAB 2", "CCCCCC 3", "ABCDEF 4"];
+        let rules = CardRules {
+            hand_size: 6,
+            rank_order: "ABCDEF".to_string(),
+            wildcards: vec!['F'],
+            tie_break: TieBreak::InOrder,
+        };
+        assert_eq!(
+            vec![
+                "ABCDEF 4: One pair, rank 1",
+                "ABABAB 2: 2 triples, rank 2",
+                "AAAAAA 1: 6 of a kind, rank 3",
+                "CCCCCC 3: 6 of a kind, rank 4",
+            ],
+            explain(sample, &rules)
+        );
+    }
+
     #[test]
     fn day_07_part_02_solution() {
         let input = include_str!("../../inputs/day_07.txt").lines().collect();
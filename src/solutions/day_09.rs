@@ -1,41 +1,111 @@
 use std::str::FromStr;
 
-pub fn solve_1(histories: Vec<&str>) -> i32 {
-    solve(histories, Direction::Future)
+use num_rational::Ratio;
+
+pub fn solve_1(histories: Vec<&str>) -> i64 {
+    solve(histories, |s| s.forward(1))
 }
 
-pub fn solve_2(histories: Vec<&str>) -> i32 {
-    solve(histories, Direction::Past)
+pub fn solve_2(histories: Vec<&str>) -> i64 {
+    solve(histories, |s| s.backward(1))
 }
 
-fn solve(histories: Vec<&str>, direction: Direction) -> i32 {
-    histories
+fn solve(histories: Vec<&str>, predict: fn(&Sequence) -> i128) -> i64 {
+    let sum: i128 = histories
         .iter()
-        .map(|h| h.split(' ').flat_map(i32::from_str))
-        .map(|h| match direction {
-            Direction::Future => h.collect(),
-            Direction::Past => h.rev().collect(),
-        })
-        .map(|h| predict(h, 0))
-        .sum()
+        .map(|h| Sequence::new(h).unwrap())
+        .map(|s| predict(&s))
+        .sum();
+
+    i64::try_from(sum).unwrap()
+}
+
+/// A history fitted to the polynomial generating it, in Newton's forward difference form
+///
+/// With `x` the index into the history, its values are `sum(leading[j] * binomial(x, j))`,
+/// where `leading[j]` is the first element of the `j`-th row of the difference table.
+#[derive(Debug)]
+pub struct Sequence {
+    len: usize,
+    leading: Vec<i128>,
 }
 
-fn predict(history: Vec<i32>, prediction: i32) -> i32 {
-    if history.iter().all(|&h| h == 0) {
-        prediction
-    } else {
-        let prediction = prediction + history.last().unwrap();
-        let history = (1..history.len())
-            .map(|i| history[i] - history[i - 1])
-            .collect();
+impl Sequence {
+    pub fn new(history: &str) -> Result<Sequence, String> {
+        let mut row: Vec<i128> = history
+            .split_whitespace()
+            .map(|h| i64::from_str(h).map(|h| h as i128))
+            .collect::<Result<_, _>>()
+            .map_err(|e| format!("Invalid history \"{history}\": {e}"))?;
+        let len = row.len();
+        let mut leading = vec![];
 
-        predict(history, prediction)
+        while row.iter().any(|&r| r != 0) {
+            if row.len() == 1 {
+                return Err(format!(
+                    "History \"{history}\" never reaches an all-zero row"
+                ));
+            }
+
+            leading.push(row[0]);
+            row = row.windows(2).map(|w| w[1] - w[0]).collect();
+        }
+
+        Ok(Sequence { len, leading })
+    }
+
+    /// Degree of the fitted polynomial, where an all-zero history has degree 0
+    pub fn degree(&self) -> usize {
+        self.leading.len().saturating_sub(1)
+    }
+
+    /// The value at index `x` of the history, which can lie before or after it
+    pub fn value(&self, x: i64) -> i128 {
+        let x = x as i128;
+        let mut binomial = 1;
+        let mut value = 0;
+
+        for (j, &leading) in self.leading.iter().enumerate() {
+            value += leading * binomial;
+            // binomial(x, j + 1) = binomial(x, j) * (x - j) / (j + 1), which always divides exactly
+            binomial = binomial * (x - j as i128) / (j as i128 + 1);
+        }
+
+        value
+    }
+
+    /// The value `k` steps after the last one of the history
+    pub fn forward(&self, k: u64) -> i128 {
+        self.value(self.len as i64 - 1 + k as i64)
     }
-}
 
-enum Direction {
-    Future,
-    Past,
+    /// The value `k` steps before the first one of the history
+    pub fn backward(&self, k: u64) -> i128 {
+        self.value(-(k as i64))
+    }
+
+    /// Coefficients of the fitted polynomial in `x`, the index into the history, starting with the constant term
+    pub fn coefficients(&self) -> Vec<Ratio<i128>> {
+        let mut coefficients = vec![Ratio::from_integer(0); self.degree() + 1];
+        // Coefficients of the falling factorial x * (x - 1) * ... * (x - j + 1), divided by j!
+        let mut falling = vec![Ratio::from_integer(1)];
+
+        for (j, &leading) in self.leading.iter().enumerate() {
+            for (c, f) in coefficients.iter_mut().zip(&falling) {
+                *c += f * leading;
+            }
+
+            let j = j as i128;
+            let mut next = vec![Ratio::from_integer(0); falling.len() + 1];
+            for (i, &f) in falling.iter().enumerate() {
+                next[i + 1] += f / (j + 1);
+                next[i] -= f * j / (j + 1);
+            }
+            falling = next;
+        }
+
+        coefficients
+    }
 }
 
 #[cfg(test)]
@@ -69,4 +139,47 @@ mod tests {
 
         assert_eq!(1_124, solve_2(input));
     }
+
+    #[test]
+    fn day_09_sequence() {
+        let sequence = Sequence::new("1 3 6 10 15 21").unwrap();
+        assert_eq!(2, sequence.degree());
+        assert_eq!(
+            vec![Ratio::new(1, 1), Ratio::new(3, 2), Ratio::new(1, 2)],
+            sequence.coefficients()
+        );
+        assert_eq!(28, sequence.forward(1));
+        assert_eq!(136, sequence.forward(10));
+        assert_eq!(0, sequence.backward(1));
+        assert_eq!(1, sequence.backward(3));
+
+        let sequence = Sequence::new("10 13 16 21 30 45").unwrap();
+        assert_eq!(3, sequence.degree());
+        assert_eq!(68, sequence.forward(1));
+        assert_eq!(5, sequence.backward(1));
+
+        let sequence = Sequence::new("0 0 0").unwrap();
+        assert_eq!(0, sequence.degree());
+        assert_eq!(vec![Ratio::new(0, 1)], sequence.coefficients());
+        assert_eq!(0, sequence.forward(5));
+
+        // Values far beyond i32, extrapolated far ahead
+        let sequence =
+            Sequence::new("0 1000000000000 8000000000000 27000000000000 64000000000000").unwrap();
+        assert_eq!(3, sequence.degree());
+        assert_eq!(
+            1_000_000_000_000_000_000_000_000_000_000,
+            sequence.forward(999_996)
+        );
+        assert_eq!(-8_000_000_000_000, sequence.backward(2));
+    }
+
+    #[test]
+    fn day_09_no_zero_row() {
+        assert_eq!(
+            "History \"1 2 4 8\" never reaches an all-zero row",
+            Sequence::new("1 2 4 8").unwrap_err()
+        );
+        assert!(Sequence::new("1 2 x").is_err());
+    }
 }
@@ -1,135 +1,230 @@
 use std::ops::{Index, Not};
 
+use itertools::Itertools;
 use rustc_hash::FxHashSet;
 
+use crate::util::geometry::Polygon;
+
+type Coord = (usize, usize);
+
 pub fn solve_1(maze: Vec<&str>) -> usize {
-    Maze::new(maze).pipe_loop.len() / 2
+    Maze::new(maze).unwrap().pipe_loop.len() / 2
 }
 
 pub fn solve_2(maze: Vec<&str>) -> usize {
-    Maze::new(maze).count_enclosed_tiles()
+    Maze::new(maze).unwrap().count_enclosed_tiles()
 }
 
 #[derive(Debug)]
-struct Maze {
+pub struct Maze {
     tiles: Vec<Vec<Tile>>,
-    pipe_loop: FxHashSet<(usize, usize)>,
+    start: (usize, usize),
+    pipe_loop: Vec<(usize, usize)>,
+    loop_tiles: FxHashSet<(usize, usize)>,
 }
 
 impl Maze {
-    fn new(maze: Vec<&str>) -> Maze {
-        let tiles: Vec<_> = maze
+    /// Parses the maze, replacing the start tile with the pipe it has to be for it to connect to exactly 2 neighbours.
+    pub fn new(maze: Vec<&str>) -> Result<Maze, String> {
+        let mut tiles: Vec<Vec<_>> = maze
             .iter()
             .map(|l| l.chars().map(Tile::new).collect())
             .collect();
-        let pipe_loop = Self::find_loop(&tiles);
 
-        Maze { tiles, pipe_loop }
+        let start = Self::find_start(&tiles).ok_or("No start tile found")?;
+        let start_tile = Self::infer_start(&tiles, start)?;
+        tiles[start.1][start.0] = start_tile;
+
+        let pipe_loop = Self::follow_loop(&tiles, start)
+            .map_err(|_| format!("Start tile at {:?} is not part of a closed loop", start))?;
+        let loop_tiles = pipe_loop.iter().copied().collect();
+
+        Ok(Maze {
+            tiles,
+            start,
+            pipe_loop,
+            loop_tiles,
+        })
     }
 
-    fn find_loop(tiles: &Vec<Vec<Tile>>) -> FxHashSet<(usize, usize)> {
-        let start = Self::find_start(tiles);
-        let mut pipe_loop = FxHashSet::default();
-        pipe_loop.insert(start);
+    /// The tiles making up the loop, formatted (x, y), in the order they are visited starting from the start tile.
+    pub fn pipe_loop(&self) -> &[(usize, usize)] {
+        &self.pipe_loop
+    }
 
-        let mut prev = start;
-        let mut current = Self::next(tiles, start, start);
+    /// The pipe hidden under the start tile.
+    pub fn start_tile(&self) -> Tile {
+        self[self.start]
+    }
+
+    /// All closed loops of pipes in the maze, including the one passing through the start tile.
+    pub fn loops(&self) -> Vec<Vec<(usize, usize)>> {
+        let mut visited = FxHashSet::default();
+        let mut loops = Vec::new();
 
-        while current != start {
-            pipe_loop.insert(current);
-            let next = Self::next(tiles, prev, current);
+        for y in 0..self.tiles.len() {
+            for x in 0..self.tiles[y].len() {
+                if visited.contains(&(x, y)) || self[(x, y)].directions().is_empty() {
+                    continue;
+                }
 
-            prev = current;
-            current = next;
+                // Tiles of an open chain can never be part of a loop, so they are not walked again
+                match Self::follow_loop(&self.tiles, (x, y)) {
+                    Ok(pipe_loop) => {
+                        visited.extend(pipe_loop.iter().copied());
+                        loops.push(pipe_loop);
+                    }
+                    Err(chain) => visited.extend(chain),
+                }
+            }
         }
 
-        pipe_loop
+        loops
     }
 
-    fn find_start(tiles: &Vec<Vec<Tile>>) -> (usize, usize) {
+    fn find_start(tiles: &[Vec<Tile>]) -> Option<(usize, usize)> {
         (0..tiles.len())
-            .flat_map(|y| (0..tiles[0].len()).map(move |x| (x, y)))
+            .flat_map(|y| (0..tiles[y].len()).map(move |x| (x, y)))
             .find(|&(x, y)| matches!(tiles[y][x], Tile::Start))
-            .unwrap()
     }
 
-    fn next(
-        tiles: &Vec<Vec<Tile>>,
-        prev: (usize, usize),
-        current: (usize, usize),
-    ) -> (usize, usize) {
-        *Self::neighbours(tiles, current)
-            .iter()
-            .find(|&&n| n != prev)
-            .unwrap()
+    fn infer_start(tiles: &[Vec<Tile>], start: (usize, usize)) -> Result<Tile, String> {
+        let connected = Direction::ALL
+            .into_iter()
+            .filter(|&d| match Self::step(tiles, start, d) {
+                Some((x, y)) => tiles[y][x].directions().contains(&d.opposite()),
+                None => false,
+            })
+            .collect_vec();
+
+        match connected[..] {
+            [first, second] => Ok(Tile::from_directions(first, second)),
+            _ => Err(format!(
+                "Start tile at {:?} connects to {} neighbours, expected exactly 2",
+                start,
+                connected.len()
+            )),
+        }
     }
 
-    fn neighbours(tiles: &Vec<Vec<Tile>>, tile: (usize, usize)) -> Vec<(usize, usize)> {
-        match tiles[tile.1][tile.0] {
-            Tile::NorthSouth => vec![(tile.0, tile.1 - 1), (tile.0, tile.1 + 1)],
-            Tile::EastWest => vec![(tile.0 + 1, tile.1), (tile.0 - 1, tile.1)],
-            Tile::NorthEast => vec![(tile.0, tile.1 - 1), (tile.0 + 1, tile.1)],
-            Tile::NorthWest => vec![(tile.0, tile.1 - 1), (tile.0 - 1, tile.1)],
-            Tile::SouthWest => vec![(tile.0, tile.1 + 1), (tile.0 - 1, tile.1)],
-            Tile::SouthEast => vec![(tile.0, tile.1 + 1), (tile.0 + 1, tile.1)],
-            Tile::Ground => vec![],
-            Tile::Start => Self::start_neighbours(tiles, tile),
+    /// Follows the pipes starting from a tile, returning the tiles visited if they form a closed loop.
+    /// Otherwise the tiles of the open chain are returned as the error, walked in both directions from the start.
+    fn follow_loop(tiles: &[Vec<Tile>], start: Coord) -> Result<Vec<Coord>, Vec<Coord>> {
+        let directions = tiles[start.1][start.0].directions();
+        let Some(&first) = directions.first() else {
+            return Err(vec![start]);
+        };
+
+        let (mut chain, closed) = Self::walk(tiles, start, first);
+        if closed {
+            return Ok(chain);
         }
+
+        if let Some(&second) = directions.get(1) {
+            chain.extend(Self::walk(tiles, start, second).0.into_iter().skip(1));
+        }
+        Err(chain)
     }
 
-    fn start_neighbours(tiles: &Vec<Vec<Tile>>, start: (usize, usize)) -> Vec<(usize, usize)> {
-        let height = tiles.len();
-        let width = tiles[0].len();
-        let mut neighbours = Vec::new();
+    /// Walks the pipes from a tile in a direction until the walk gets back to the tile or the pipes stop connecting.
+    /// Returns the tiles walked, starting with the tile itself, and whether they form a closed loop.
+    fn walk(tiles: &[Vec<Tile>], start: Coord, mut direction: Direction) -> (Vec<Coord>, bool) {
+        let mut walked = vec![start];
+        let mut current = start;
 
-        if start.0 >= 1 {
-            if let Tile::EastWest | Tile::NorthEast | Tile::SouthEast = &tiles[start.1][start.0 - 1]
-            {
-                neighbours.push((start.0 - 1, start.1));
+        loop {
+            current = match Self::step(tiles, current, direction) {
+                Some(next) => next,
+                None => return (walked, false),
+            };
+            if current == start {
+                return (walked, true);
             }
-        }
 
-        if start.1 >= 1 {
-            if let Tile::NorthSouth | Tile::SouthWest | Tile::SouthEast =
-                &tiles[start.1 - 1][start.0]
-            {
-                neighbours.push((start.0, start.1 - 1));
+            let directions = tiles[current.1][current.0].directions();
+            if directions.contains(&direction.opposite()).not() {
+                return (walked, false);
             }
-        }
 
-        if start.0 < width - 1 {
-            if let Tile::EastWest | Tile::NorthWest | Tile::SouthWest = &tiles[start.1][start.0 + 1]
-            {
-                neighbours.push((start.0 + 1, start.1));
-            }
+            walked.push(current);
+            direction = directions
+                .into_iter()
+                .find(|&d| d != direction.opposite())
+                .unwrap();
         }
+    }
 
-        if start.1 < height - 1 {
-            if let Tile::NorthSouth | Tile::NorthEast | Tile::NorthWest =
-                &tiles[start.1 + 1][start.0]
-            {
-                neighbours.push((start.0, start.1 + 1));
-            }
-        }
+    /// The position one step in the direction, as long as it lies within the maze.
+    fn step(
+        tiles: &[Vec<Tile>],
+        tile: (usize, usize),
+        direction: Direction,
+    ) -> Option<(usize, usize)> {
+        let (x, y) = match direction {
+            Direction::North => (Some(tile.0), tile.1.checked_sub(1)),
+            Direction::East => (Some(tile.0 + 1), Some(tile.1)),
+            Direction::South => (Some(tile.0), Some(tile.1 + 1)),
+            Direction::West => (tile.0.checked_sub(1), Some(tile.1)),
+        };
+        let (x, y) = (x?, y?);
+
+        (y < tiles.len() && x < tiles[y].len()).then_some((x, y))
+    }
+
+    pub fn count_enclosed_tiles(&self) -> usize {
+        self.enclosed_tiles().len()
+    }
+
+    /// Alternative to the diagonal rays, using the loop's tiles as the vertices of a polygon.
+    ///
+    /// The shoelace formula gives the area A of this polygon,
+    /// Pick's theorem (A = i + b/2 - 1) then gives the number of interior points i, with b the loop length.
+    pub fn count_enclosed_tiles_shoelace(&self) -> usize {
+        let vertices = self
+            .pipe_loop
+            .iter()
+            .map(|&(x, y)| (x as i64, y as i64))
+            .collect();
+
+        Polygon::new(vertices).interior_points() as usize
+    }
 
-        neighbours
+    /// Draws the maze using box-drawing characters, with heavy lines for the loop, and "I" for the enclosed tiles.
+    pub fn render(&self) -> String {
+        let enclosed = self.enclosed_tiles();
+
+        (0..self.tiles.len())
+            .map(|y| {
+                (0..self.tiles[0].len())
+                    .map(|x| {
+                        if enclosed.contains(&(x, y)) {
+                            'I'
+                        } else if (x, y) == self.start {
+                            'S'
+                        } else {
+                            self[(x, y)].render(self.loop_tiles.contains(&(x, y)))
+                        }
+                    })
+                    .collect::<String>()
+            })
+            .join("\n")
     }
 
-    fn count_enclosed_tiles(&self) -> usize {
+    fn enclosed_tiles(&self) -> FxHashSet<(usize, usize)> {
         let horizontal_starts: Vec<_> = (0..self.tiles[0].len()).map(|x| (x, 0usize)).collect();
         let vertical_starts: Vec<_> = (1..self.tiles.len()).map(|y| (0usize, y)).collect();
 
         horizontal_starts
             .into_iter()
             .chain(vertical_starts)
-            .map(|s| Self::diagonal_ray(self, s))
-            .sum()
+            .flat_map(|s| Self::diagonal_ray(self, s))
+            .collect()
     }
 
-    fn diagonal_ray(&self, start: (usize, usize)) -> usize {
+    fn diagonal_ray(&self, start: (usize, usize)) -> Vec<(usize, usize)> {
         let mut inside = false;
         let mut current = start;
-        let mut inside_points = 0;
+        let mut inside_points = Vec::new();
 
         let width = self.tiles[0].len();
         let height = self.tiles.len();
@@ -137,14 +232,14 @@ impl Maze {
         while current.0 < width && current.1 < height {
             let tile = &self[current];
 
-            if self.pipe_loop.contains(&current) {
+            if self.loop_tiles.contains(&current) {
                 // The diagonal line "grazes" these specific 2 pipes
                 // Because no crossing between outside/inside occurred, we simply ignore this tile
                 if matches!(tile, Tile::NorthEast).not() && matches!(tile, Tile::SouthWest).not() {
                     inside = inside.not()
                 }
             } else if inside {
-                inside_points += 1
+                inside_points.push(current)
             }
 
             current = (current.0 + 1, current.1 + 1);
@@ -163,8 +258,8 @@ impl Index<(usize, usize)> for Maze {
     }
 }
 
-#[derive(Debug)]
-enum Tile {
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Tile {
     NorthSouth,
     EastWest,
     NorthEast,
@@ -189,6 +284,75 @@ impl Tile {
             _ => panic!("Received invalid tile: {tile}"),
         }
     }
+
+    fn from_directions(first: Direction, second: Direction) -> Tile {
+        match (first, second) {
+            (Direction::North, Direction::South) => Tile::NorthSouth,
+            (Direction::East, Direction::West) => Tile::EastWest,
+            (Direction::North, Direction::East) => Tile::NorthEast,
+            (Direction::North, Direction::West) => Tile::NorthWest,
+            (Direction::South, Direction::West) => Tile::SouthWest,
+            (Direction::East, Direction::South) => Tile::SouthEast,
+            _ => Self::from_directions(second, first),
+        }
+    }
+
+    fn directions(&self) -> Vec<Direction> {
+        match self {
+            Tile::NorthSouth => vec![Direction::North, Direction::South],
+            Tile::EastWest => vec![Direction::East, Direction::West],
+            Tile::NorthEast => vec![Direction::North, Direction::East],
+            Tile::NorthWest => vec![Direction::North, Direction::West],
+            Tile::SouthWest => vec![Direction::South, Direction::West],
+            Tile::SouthEast => vec![Direction::East, Direction::South],
+            Tile::Ground | Tile::Start => vec![],
+        }
+    }
+
+    fn render(&self, in_loop: bool) -> char {
+        match (self, in_loop) {
+            (Tile::NorthSouth, false) => '│',
+            (Tile::EastWest, false) => '─',
+            (Tile::NorthEast, false) => '└',
+            (Tile::NorthWest, false) => '┘',
+            (Tile::SouthWest, false) => '┐',
+            (Tile::SouthEast, false) => '┌',
+            (Tile::NorthSouth, true) => '┃',
+            (Tile::EastWest, true) => '━',
+            (Tile::NorthEast, true) => '┗',
+            (Tile::NorthWest, true) => '┛',
+            (Tile::SouthWest, true) => '┓',
+            (Tile::SouthEast, true) => '┏',
+            (Tile::Ground, _) => '.',
+            (Tile::Start, _) => 'S',
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum Direction {
+    North,
+    East,
+    South,
+    West,
+}
+
+impl Direction {
+    const ALL: [Direction; 4] = [
+        Direction::North,
+        Direction::East,
+        Direction::South,
+        Direction::West,
+    ];
+
+    fn opposite(&self) -> Direction {
+        match self {
+            Direction::North => Direction::South,
+            Direction::East => Direction::West,
+            Direction::South => Direction::North,
+            Direction::West => Direction::East,
+        }
+    }
 }
 
 #[cfg(test)]
@@ -271,7 +435,136 @@ mod tests {
             "L7JLJL-JLJLJL--JLJ.L",
         ];
 
-        assert_eq!(10, solve_2(sample));
+        assert_eq!(10, solve_2(sample.clone()));
+        assert_eq!(
+            10,
+            Maze::new(sample).unwrap().count_enclosed_tiles_shoelace()
+        );
+    }
+
+    #[test]
+    fn day_10_shoelace() {
+        let samples = vec![
+            vec!["S7", "LJ"],
+            vec!["-L|F7", "7S-7|", "L|7||", "-L-J|", "L|-JF"],
+            vec!["7-F7-", ".FJ|7", "SJLL7", "|F--J", "LJ.LJ"],
+            vec![
+                ".F----7F7F7F7F-7....",
+                ".|F--7||||||||FJ....",
+                ".||.FJ||||||||L7....",
+                "FJL7L7LJLJ||LJ.L-7..",
+                "L--J.L7...LJS7F-7L7.",
+                "....F-J..F7FJ|L7L7L7",
+                "....L7.F7||L7|.L7L7|",
+                ".....|FJLJ|FJ|F7|.LJ",
+                "....FJL-7.||.||||...",
+                "....L---J.LJ.LJLJ...",
+            ],
+        ];
+
+        for sample in samples {
+            let maze = Maze::new(sample).unwrap();
+            assert_eq!(
+                maze.count_enclosed_tiles(),
+                maze.count_enclosed_tiles_shoelace()
+            );
+        }
+
+        assert_eq!(
+            0,
+            Maze::new(vec!["S7", "LJ"])
+                .unwrap()
+                .count_enclosed_tiles_shoelace()
+        );
+    }
+
+    #[test]
+    fn day_10_start_tile() {
+        let maze = Maze::new(vec!["-L|F7", "7S-7|", "L|7||", "-L-J|", "L|-JF"]).unwrap();
+        assert_eq!(Tile::SouthEast, maze.start_tile());
+
+        let maze = Maze::new(vec!["7-F7-", ".FJ|7", "SJLL7", "|F--J", "LJ.LJ"]).unwrap();
+        assert_eq!(Tile::SouthEast, maze.start_tile());
+
+        let maze = Maze::new(vec![".....", ".F-7.", ".|.|.", ".L-S.", "....."]).unwrap();
+        assert_eq!(Tile::NorthWest, maze.start_tile());
+
+        assert_eq!(
+            Err("Start tile at (1, 1) connects to 0 neighbours, expected exactly 2".to_string()),
+            Maze::new(vec!["...", ".S.", "..."]).map(|m| m.start_tile())
+        );
+        assert_eq!(
+            Err("Start tile at (1, 1) connects to 4 neighbours, expected exactly 2".to_string()),
+            Maze::new(vec![".|.", "-S-", ".|."]).map(|m| m.start_tile())
+        );
+        assert_eq!(
+            Err("Start tile at (1, 0) is not part of a closed loop".to_string()),
+            Maze::new(vec!["-S-"]).map(|m| m.start_tile())
+        );
+        assert_eq!(
+            Err("No start tile found".to_string()),
+            Maze::new(vec!["F7", "LJ"]).map(|m| m.start_tile())
+        );
+    }
+
+    #[test]
+    fn day_10_multiple_loops() {
+        let sample = vec!["F7.F-7", "LJ.|.|", "F7.S-J", "|L-7..", "L--J.."];
+        let maze = Maze::new(sample).unwrap();
+
+        assert_eq!(Tile::NorthEast, maze.start_tile());
+        assert_eq!(3, maze.loops().len());
+        assert_eq!(
+            vec![4, 8, 10],
+            maze.loops().iter().map(|l| l.len()).sorted().collect_vec()
+        );
+        assert_eq!(8, maze.pipe_loop().len());
+
+        let sample = vec!["F7.F-7", "LJ.|.|", "..|S-J", "F-L7--"];
+        let maze = Maze::new(sample).unwrap();
+        assert_eq!(
+            vec![4, 8],
+            maze.loops().iter().map(|l| l.len()).sorted().collect_vec()
+        );
+        assert_eq!(
+            Err(vec![(2, 3), (2, 2), (3, 3)]),
+            Maze::follow_loop(&maze.tiles, (2, 3))
+        );
+        assert_eq!(
+            Err(vec![(1, 3), (0, 3)]),
+            Maze::follow_loop(&maze.tiles, (1, 3))
+        );
+    }
+
+    #[test]
+    fn day_10_render() {
+        let sample = vec![
+            "...........",
+            ".S-------7.",
+            ".|F-----7|.",
+            ".||.....||.",
+            ".||.....||.",
+            ".|L-7.F-J|.",
+            ".|..|.|..|.",
+            ".L--J.L--J.",
+            "..........-",
+        ];
+        let maze = Maze::new(sample).unwrap();
+
+        assert_eq!(
+            "...........\n\
+             .S━━━━━━━┓.\n\
+             .┃┏━━━━━┓┃.\n\
+             .┃┃.....┃┃.\n\
+             .┃┃.....┃┃.\n\
+             .┃┗━┓.┏━┛┃.\n\
+             .┃II┃.┃II┃.\n\
+             .┗━━┛.┗━━┛.\n\
+             ..........─",
+            maze.render()
+        );
+        assert_eq!(46, maze.pipe_loop().len());
+        assert_eq!(&[(1, 1), (2, 1), (3, 1)], &maze.pipe_loop()[..3]);
     }
 
     #[test]
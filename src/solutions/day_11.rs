@@ -1,96 +1,142 @@
-use std::ops::{Not, Range};
-
 use itertools::Itertools;
-use rustc_hash::FxHashSet;
 
 pub fn solve_1(image: Vec<&str>) -> u64 {
-    Space::new(image).expand(1).distance_sums()
+    Space::new(image).manhattan_sum(1)
 }
 
 pub fn solve_2(image: Vec<&str>, increase: i64) -> u64 {
-    Space::new(image).expand(increase).distance_sums()
+    Space::new(image).manhattan_sum(increase)
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Metric {
+    Manhattan,
+    Chebyshev,
+    Euclidean,
 }
 
+impl Metric {
+    fn distance(&self, g_1: (i64, i64), g_2: (i64, i64)) -> f64 {
+        let dx = (g_1.0 - g_2.0).abs();
+        let dy = (g_1.1 - g_2.1).abs();
+
+        match self {
+            Metric::Manhattan => (dx + dy) as f64,
+            Metric::Chebyshev => dx.max(dy) as f64,
+            Metric::Euclidean => (dx as f64).hypot(dy as f64),
+        }
+    }
+}
+
+/// Galaxies are named 1 through n, in reading order of the image
+///
+/// Expanding space inserts `increase` extra rows (columns) after every empty row (column).
 #[derive(Debug)]
-struct Space {
-    galaxies: FxHashSet<(i64, i64)>,
-    horizontal: Range<i64>,
-    vertical: Range<i64>,
+pub struct Space {
+    galaxies: Vec<(i64, i64)>,
+    empty_rows: Vec<i64>,
+    empty_cols: Vec<i64>,
 }
 
 impl Space {
-    fn new(image: Vec<&str>) -> Space {
+    pub fn new(image: Vec<&str>) -> Space {
         let image = image.iter().map(|&l| l.chars().collect_vec()).collect_vec();
 
         let galaxies = (0..image.len())
             .flat_map(|y| (0..image[0].len()).map(move |x| (x, y)))
             .filter(|&(x, y)| image[y][x] == '#')
             .map(|(x, y)| (x as i64, y as i64))
+            .collect_vec();
+        let empty_rows = (0..image.len() as i64)
+            .filter(|&y| galaxies.iter().all(|g| g.1 != y))
+            .collect();
+        let empty_cols = (0..image[0].len() as i64)
+            .filter(|&x| galaxies.iter().all(|g| g.0 != x))
             .collect();
-        let horizontal = 0..(image[0].len() as i64);
-        let vertical = 0..(image.len() as i64);
 
         Space {
             galaxies,
-            horizontal,
-            vertical,
+            empty_rows,
+            empty_cols,
         }
     }
 
-    fn expand(&self, increase: i64) -> Space {
-        let empty_rows = Self::empty_rows(self);
-        let empty_cols = Self::empty_cols(self);
+    /// Sum of the Manhattan distances between all pairs of galaxies
+    pub fn manhattan_sum(&self, increase: i64) -> u64 {
+        let (xs, ys): (Vec<_>, Vec<_>) = self.expanded(increase).into_iter().unzip();
 
-        let mut galaxies = self.galaxies.clone();
-
-        empty_rows.iter().rev().for_each(|&r| {
-            galaxies = galaxies
-                .iter()
-                .map(|&g| if g.1 > r { (g.0, g.1 + increase) } else { g })
-                .collect()
-        });
+        Self::pairwise_sum(xs) + Self::pairwise_sum(ys)
+    }
 
-        empty_cols.iter().rev().for_each(|&c| {
-            galaxies = galaxies
-                .iter()
-                .map(|&g| if g.0 > c { (g.0 + increase, g.1) } else { g })
-                .collect()
-        });
+    /// Sum of the Chebyshev distances between all pairs of galaxies
+    ///
+    /// Rotating by 45 degrees turns Chebyshev distances into (halved) Manhattan distances.
+    pub fn chebyshev_sum(&self, increase: i64) -> u64 {
+        let (us, vs): (Vec<_>, Vec<_>) = self
+            .expanded(increase)
+            .into_iter()
+            .map(|(x, y)| (x + y, x - y))
+            .unzip();
+
+        (Self::pairwise_sum(us) + Self::pairwise_sum(vs)) / 2
+    }
 
-        Space {
-            galaxies,
-            horizontal: 0..self.horizontal.end + empty_rows.len() as i64,
-            vertical: 0..self.vertical.end + empty_cols.len() as i64,
+    /// Sum of the distances between all pairs of galaxies
+    ///
+    /// Euclidean distances can not be decomposed per axis, so they take quadratic time.
+    pub fn distance_sum(&self, increase: i64, metric: Metric) -> f64 {
+        match metric {
+            Metric::Manhattan => self.manhattan_sum(increase) as f64,
+            Metric::Chebyshev => self.chebyshev_sum(increase) as f64,
+            Metric::Euclidean => self
+                .expanded(increase)
+                .into_iter()
+                .tuple_combinations()
+                .map(|(g_1, g_2)| metric.distance(g_1, g_2))
+                .sum(),
         }
     }
 
-    fn empty_rows(&self) -> Vec<i64> {
-        self.vertical
-            .clone()
-            .filter(|y| self.galaxies.iter().any(|g| g.1 == *y).not())
-            .collect()
+    /// Distance between the galaxies named `from` and `to`, if both exist
+    pub fn distance(&self, from: usize, to: usize, increase: i64, metric: Metric) -> Option<f64> {
+        let galaxy = |name: usize| {
+            name.checked_sub(1)
+                .and_then(|i| self.galaxies.get(i))
+                .map(|&g| self.expand(g, increase))
+        };
+
+        Some(metric.distance(galaxy(from)?, galaxy(to)?))
     }
 
-    fn empty_cols(&self) -> Vec<i64> {
-        self.horizontal
-            .clone()
-            .filter(|x| self.galaxies.iter().any(|g| g.0 == *x).not())
-            .collect()
+    pub fn nr_galaxies(&self) -> usize {
+        self.galaxies.len()
     }
 
-    fn distance_sums(&self) -> u64 {
+    fn expand(&self, (x, y): (i64, i64), increase: i64) -> (i64, i64) {
+        let cols_before = self.empty_cols.partition_point(|&c| c < x) as i64;
+        let rows_before = self.empty_rows.partition_point(|&r| r < y) as i64;
+
+        (x + cols_before * increase, y + rows_before * increase)
+    }
+
+    fn expanded(&self, increase: i64) -> Vec<(i64, i64)> {
         self.galaxies
             .iter()
-            .map(|g_1| {
-                self.galaxies
-                    .iter()
-                    .filter(|&g_2| g_1 != g_2)
-                    .map(|g_2| i64::abs(g_1.0 - g_2.0) + i64::abs(g_1.1 - g_2.1))
-                    .map(|d| d as u64)
-                    .sum::<u64>()
+            .map(|&g| self.expand(g, increase))
+            .collect()
+    }
+
+    /// Sum of |a - b| over all pairs, after sorting every element is larger than all those before it
+    fn pairwise_sum(mut coordinates: Vec<i64>) -> u64 {
+        coordinates.sort_unstable();
+
+        coordinates
+            .iter()
+            .enumerate()
+            .fold((0, 0), |(sum, prefix), (i, &c)| {
+                (sum + i as i128 * c as i128 - prefix, prefix + c as i128)
             })
-            .sum::<u64>()
-            / 2
+            .0 as u64
     }
 }
 
@@ -148,4 +194,45 @@ mod tests {
 
         assert_eq!(840_988_812_853, solve_2(input, 1000000 - 1));
     }
+
+    #[test]
+    fn day_11_metrics() {
+        let sample = vec![
+            "...#......",
+            ".......#..",
+            "#.........",
+            "..........",
+            "......#...",
+            ".#........",
+            ".........#",
+            "..........",
+            ".......#..",
+            "#...#.....",
+        ];
+        let space = Space::new(sample);
+
+        assert_eq!(9, space.nr_galaxies());
+        assert_eq!(Some(9.0), space.distance(5, 9, 1, Metric::Manhattan));
+        assert_eq!(Some(15.0), space.distance(1, 7, 1, Metric::Manhattan));
+        assert_eq!(Some(17.0), space.distance(3, 6, 1, Metric::Manhattan));
+        assert_eq!(Some(5.0), space.distance(8, 9, 1, Metric::Manhattan));
+        assert_eq!(Some(5.0), space.distance(5, 9, 1, Metric::Chebyshev));
+        assert_eq!(
+            Some(41_f64.sqrt()),
+            space.distance(5, 9, 1, Metric::Euclidean)
+        );
+        assert_eq!(None, space.distance(0, 9, 1, Metric::Manhattan));
+        assert_eq!(None, space.distance(1, 10, 1, Metric::Manhattan));
+
+        for increase in [0, 1, 9, 99] {
+            for metric in [Metric::Manhattan, Metric::Chebyshev] {
+                let brute_force: f64 = (1..=9)
+                    .tuple_combinations()
+                    .map(|(a, b)| space.distance(a, b, increase, metric).unwrap())
+                    .sum();
+
+                assert_eq!(brute_force, space.distance_sum(increase, metric));
+            }
+        }
+    }
 }
@@ -1,32 +1,33 @@
 use std::str::FromStr;
 
 use itertools::Itertools;
-use rustc_hash::FxHashMap;
+use rand::Rng;
 
 pub fn solve_1(records: Vec<&str>) -> u64 {
-    solve(records, false)
+    solve(records, 1)
 }
 
 pub fn solve_2(records: Vec<&str>) -> u64 {
-    solve(records, true)
+    solve(records, 5)
 }
 
-fn solve(records: Vec<&str>, expand: bool) -> u64 {
+/// Sums the number of arrangements of all records, after unfolding each of them `unfold` times.
+pub fn solve(records: Vec<&str>, unfold: usize) -> u64 {
     records
         .iter()
-        .map(|&r| Record::new(r))
-        .map(|r| r.nr_arrangements(expand))
+        .map(|&r| Record::new(r).unfold(unfold))
+        .map(|r| r.nr_arrangements())
         .sum()
 }
 
-#[derive(Debug, Eq, PartialEq, Clone, Hash)]
-struct Record {
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct Record {
     springs: Vec<Condition>,
     criteria: Vec<usize>,
 }
 
 impl Record {
-    fn new(record: &str) -> Self {
+    pub fn new(record: &str) -> Self {
         let split = record.split(' ').collect_vec();
         let springs = split[0]
             .chars()
@@ -45,104 +46,169 @@ impl Record {
         Self { springs, criteria }
     }
 
-    fn nr_arrangements(self, expand: bool) -> u64 {
-        let mut memo = FxHashMap::default();
-        let record = if expand { self.expand() } else { self };
+    /// Counts the arrangements by walking over the springs once,
+    /// tracking how many ways there are to end up in every (group index, run length) state.
+    ///
+    /// The group index is the number of damaged groups completed so far,
+    /// the run length the number of damaged springs in the group currently being built.
+    /// Both state tables are allocated once up front and swapped after every spring.
+    pub fn nr_arrangements(&self) -> u64 {
+        let nr_groups = self.criteria.len();
+        let max_run = self.criteria.iter().max().copied().unwrap_or(0);
+        let idx = |group: usize, run: usize| group * (max_run + 1) + run;
+
+        let mut current = vec![0u64; (nr_groups + 1) * (max_run + 1)];
+        let mut next = current.clone();
+        current[idx(0, 0)] = 1;
+
+        for &spring in &self.springs {
+            next.fill(0);
+
+            for group in 0..=nr_groups {
+                for run in 0..=max_run {
+                    let count = current[idx(group, run)];
+                    if count == 0 {
+                        continue;
+                    }
 
-        Self::nr_arrangements_rec(record, &mut memo)
-    }
+                    // An operational spring either continues a gap, or closes a group of exactly the right size
+                    if spring != Condition::Damaged {
+                        if run == 0 {
+                            next[idx(group, 0)] += count;
+                        } else if run == self.criteria[group] {
+                            next[idx(group + 1, 0)] += count;
+                        }
+                    }
+
+                    // A damaged spring extends the current group, as long as it does not grow too large
+                    if spring != Condition::Operational
+                        && group < nr_groups
+                        && run < self.criteria[group]
+                    {
+                        next[idx(group, run + 1)] += count;
+                    }
+                }
+            }
 
-    fn nr_arrangements_rec(self, memo: &mut FxHashMap<Record, u64>) -> u64 {
-        if let Some(v) = memo.get(&self) {
-            return *v;
+            std::mem::swap(&mut current, &mut next);
         }
 
-        let record = match Self::simplify(self.clone()) {
-            Ok(r) => r,
-            Err(RecordStatus::Valid) => return 1,
-            Err(RecordStatus::Invalid) => return 0,
+        // Either all groups were closed already, or the springs end in the last group
+        let closed = current[idx(nr_groups, 0)];
+        let trailing = match self.criteria.last() {
+            Some(&last) => current[idx(nr_groups - 1, last)],
+            None => 0,
         };
 
-        let mut variant_operational = record.clone();
-        variant_operational.springs[0] = Condition::Operational;
+        closed + trailing
+    }
 
-        let mut variant_damaged = record.clone();
-        variant_damaged.springs[0] = Condition::Damaged;
+    /// Unfolds the record `n` times, joining the copies of the springs with an unknown spring.
+    pub fn unfold(&self, n: usize) -> Self {
+        let springs = vec![self.springs.clone(); n].join(&Condition::Unknown);
+        let criteria = self.criteria.repeat(n);
 
-        let nr_arrangements = Self::nr_arrangements_rec(variant_operational, memo)
-            + Self::nr_arrangements_rec(variant_damaged, memo);
+        Self { springs, criteria }
+    }
 
-        memo.insert(self, nr_arrangements);
+    /// Lazily yields every valid arrangement, with all unknown springs filled in, e.g. `#.#.###`.
+    ///
+    /// Each arrangement is built directly from its index, so skipping ahead or taking only a few
+    /// stays cheap even when the total number of arrangements is huge.
+    pub fn arrangements(&self) -> impl Iterator<Item = String> + '_ {
+        let ways = self.ways();
+        let total = ways[0];
 
-        nr_arrangements
+        (0..total).map(move |nr| self.arrangement(&ways, nr))
     }
 
-    fn simplify(self) -> Result<Self, RecordStatus> {
-        let Self {
-            mut springs,
-            mut criteria,
-        } = self;
+    /// Picks a valid arrangement uniformly at random, or `None` if there is no valid arrangement.
+    pub fn sample_arrangement<R: Rng>(&self, rng: &mut R) -> Option<String> {
+        let ways = self.ways();
 
-        loop {
-            if springs.is_empty() {
-                let status = match criteria.is_empty() {
-                    true => RecordStatus::Valid,
-                    false => RecordStatus::Invalid,
-                };
-                return Err(status);
-            }
+        match ways[0] {
+            0 => None,
+            total => Some(self.arrangement(&ways, rng.gen_range(0..total))),
+        }
+    }
 
-            match springs[0] {
-                Condition::Damaged => {} // This condition is implicitly handled after the match statement
-                Condition::Operational => {
-                    springs = springs.into_iter().skip(1).collect();
-                    continue;
-                }
-                Condition::Unknown => return Ok(Self { springs, criteria }),
+    /// Table with the number of ways to arrange the springs from a position onwards, with the groups from an index onwards,
+    /// assuming the spring right before that position is not damaged.
+    ///
+    /// Position `p` and group index `g` are found at index `p * (nr_groups + 1) + g`.
+    fn ways(&self) -> Vec<u64> {
+        let nr_springs = self.springs.len();
+        let nr_groups = self.criteria.len();
+        let mut ways = vec![0u64; (nr_springs + 1) * (nr_groups + 1)];
+        ways[nr_springs * (nr_groups + 1) + nr_groups] = 1;
+
+        for pos in (0..nr_springs).rev() {
+            for group in 0..=nr_groups {
+                let (operational, damaged) = self.options(&ways, pos, group);
+                ways[pos * (nr_groups + 1) + group] = operational + damaged;
             }
+        }
 
-            if criteria.is_empty() {
-                return Err(RecordStatus::Invalid);
-            }
+        ways
+    }
 
-            let criterion = criteria[0];
-            criteria = criteria.into_iter().skip(1).collect();
+    /// The number of arrangements when the spring at the given position is operational,
+    /// and the number when it starts the next damaged group.
+    fn options(&self, ways: &[u64], pos: usize, group: usize) -> (u64, u64) {
+        let nr_groups = self.criteria.len();
+        let at = |p: usize, g: usize| ways[p * (nr_groups + 1) + g];
 
-            if springs.len() < criterion {
-                return Err(RecordStatus::Invalid);
+        let operational = match self.springs[pos] {
+            Condition::Damaged => 0,
+            _ => at(pos + 1, group),
+        };
+
+        let damaged = match self.criteria.get(group) {
+            Some(&len) if self.fits_group(pos, len) => {
+                at((pos + len + 1).min(self.springs.len()), group + 1)
             }
+            _ => 0,
+        };
 
-            if springs
+        (operational, damaged)
+    }
+
+    /// Whether a damaged group of the given length can start at the given position,
+    /// being followed by either the end of the springs or a spring that can be operational.
+    fn fits_group(&self, pos: usize, len: usize) -> bool {
+        pos + len <= self.springs.len()
+            && self.springs[pos..pos + len]
                 .iter()
-                .take(criterion)
-                .all(|s| matches!(s, Condition::Damaged) || matches!(s, Condition::Unknown))
-            {
-                if springs.len() > criterion {
-                    if matches!(springs[criterion], Condition::Damaged) {
-                        return Err(RecordStatus::Invalid);
-                    } else {
-                        springs = springs.into_iter().skip(criterion + 1).collect();
-                    }
-                } else {
-                    springs = springs.into_iter().skip(criterion).collect();
-                }
-            } else {
-                return Err(RecordStatus::Invalid);
-            }
-        }
+                .all(|&s| s != Condition::Operational)
+            && self.springs.get(pos + len) != Some(&Condition::Damaged)
     }
 
-    fn expand(self) -> Self {
-        let Self {
-            springs: s,
-            criteria: c,
-        } = self;
-        let u = vec![Condition::Unknown];
+    /// Builds the arrangement with the given index, out of all arrangements in lexicographic order ('.' before '#').
+    fn arrangement(&self, ways: &[u64], mut nr: u64) -> String {
+        let mut arrangement = String::with_capacity(self.springs.len());
+        let (mut pos, mut group) = (0, 0);
 
-        let springs = [&s, &u, &s, &u, &s, &u, &s, &u, &s[..]].concat();
-        let criteria = [&c, &c, &c, &c, &c[..]].concat();
+        while pos < self.springs.len() {
+            let (operational, _) = self.options(ways, pos, group);
 
-        Self { springs, criteria }
+            if nr < operational {
+                arrangement.push('.');
+                pos += 1;
+            } else {
+                nr -= operational;
+
+                let len = self.criteria[group];
+                (0..len).for_each(|_| arrangement.push('#'));
+                if pos + len < self.springs.len() {
+                    arrangement.push('.');
+                }
+
+                pos = (pos + len + 1).min(self.springs.len());
+                group += 1;
+            }
+        }
+
+        arrangement
     }
 }
 
@@ -153,14 +219,11 @@ enum Condition {
     Unknown,
 }
 
-#[derive(Debug, Eq, PartialEq, Copy, Clone, Hash)]
-enum RecordStatus {
-    Valid,
-    Invalid,
-}
-
 #[cfg(test)]
 mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
     use super::*;
 
     #[test]
@@ -198,6 +261,91 @@ mod tests {
         assert_eq!(525_152, solve_2(sample));
     }
 
+    #[test]
+    fn day_12_unfold() {
+        let record = Record::new("?###???????? 3,2,1");
+
+        assert_eq!(1, record.unfold(0).nr_arrangements());
+        assert_eq!(10, record.unfold(1).nr_arrangements());
+        assert_eq!(150, record.unfold(2).nr_arrangements());
+        assert_eq!(506_250, record.unfold(5).nr_arrangements());
+        assert_eq!(
+            Record::new("?###??????????###??????????###???????? 3,2,1,3,2,1,3,2,1"),
+            record.unfold(3)
+        );
+    }
+
+    #[test]
+    fn day_12_arrangements() {
+        let record = Record::new("???.### 1,1,3");
+        assert_eq!(vec!["#.#.###"], record.arrangements().collect_vec());
+
+        let record = Record::new(".??..??...?##. 1,1,3");
+        assert_eq!(
+            vec![
+                "..#...#...###.",
+                "..#..#....###.",
+                ".#....#...###.",
+                ".#...#....###."
+            ],
+            record.arrangements().collect_vec()
+        );
+
+        let record = Record::new("?###???????? 3,2,1");
+        let arrangements = record.arrangements().collect_vec();
+        assert_eq!(record.nr_arrangements(), arrangements.len() as u64);
+        assert!(arrangements.iter().all_unique());
+        assert!(arrangements.iter().all(|a| is_valid(&record, a)));
+
+        let record = Record::new("#.# 2");
+        assert_eq!(0, record.arrangements().count());
+        assert_eq!(None, record.sample_arrangement(&mut rand::thread_rng()));
+
+        // Even with an enormous number of arrangements, taking a few is instant
+        let record = Record::new("?###???????? 3,2,1").unfold(5);
+        assert_eq!(506_250, record.nr_arrangements());
+        let arrangements = record.arrangements().take(100).collect_vec();
+        assert!(arrangements.iter().all_unique());
+        assert!(arrangements.iter().all(|a| is_valid(&record, a)));
+    }
+
+    fn is_valid(record: &Record, arrangement: &str) -> bool {
+        let matches_springs =
+            record
+                .springs
+                .iter()
+                .zip(arrangement.chars())
+                .all(|(&s, c)| match s {
+                    Condition::Operational => c == '.',
+                    Condition::Damaged => c == '#',
+                    Condition::Unknown => true,
+                });
+        let groups = arrangement
+            .split('.')
+            .filter(|g| !g.is_empty())
+            .map(|g| g.len())
+            .collect_vec();
+
+        arrangement.len() == record.springs.len() && matches_springs && groups == record.criteria
+    }
+
+    #[test]
+    fn day_12_sample_arrangement() {
+        let mut rng = StdRng::seed_from_u64(12);
+        let record = Record::new("?###???????? 3,2,1");
+        let arrangements = record.arrangements().collect_vec();
+
+        let mut counts = vec![0; arrangements.len()];
+        for _ in 0..10_000 {
+            let sample = record.sample_arrangement(&mut rng).unwrap();
+            let idx = arrangements.iter().position(|a| *a == sample).unwrap();
+            counts[idx] += 1;
+        }
+
+        // With 10 arrangements, each one should be hit close to 1000 times
+        assert!(counts.iter().all(|&c| (800..1200).contains(&c)));
+    }
+
     #[test]
     fn day_12_part_02_solution() {
         let input = include_str!("../../inputs/day_12.txt").lines().collect();
@@ -4,25 +4,33 @@ use std::fmt::{self, Formatter};
 use itertools::Itertools;
 
 pub fn solve_1(ash: &str) -> usize {
-    solve(ash, &(|p| p.find_mirror()))
+    solve(ash, 0)
 }
 
 pub fn solve_2(ash: &str) -> usize {
-    solve(ash, &(|p| p.repair_smudge()))
+    solve(ash, 1)
 }
 
-fn solve(ash: &str, finder: &dyn Fn(Pattern) -> Mirror) -> usize {
-    ash.split("\n\n")
-        .map(Pattern::new)
-        .map(finder)
-        .map(|m| match m.alignment {
-            Align::Horizontal => 100 * m.location,
-            Align::Vertical => m.location,
+/// Summarises the mirrors of all patterns, when every pattern has exactly `smudges` smudges.
+pub fn solve(ash: &str, smudges: usize) -> usize {
+    reflections(ash, smudges)
+        .iter()
+        .map(|r| match r.mirror.alignment {
+            Align::Horizontal => 100 * r.mirror.location,
+            Align::Vertical => r.mirror.location,
         })
         .sum()
 }
 
-#[derive(Debug, Clone)]
+/// For every pattern, the single mirror that appears when exactly `smudges` smudges are repaired.
+pub fn reflections(ash: &str, smudges: usize) -> Vec<Reflection> {
+    ash.split("\n\n")
+        .map(Pattern::new)
+        .map(|p| Pattern::single(p.find_mirrors(smudges)))
+        .collect()
+}
+
+#[derive(Debug)]
 struct Pattern {
     tiles: Vec<Vec<Tile>>,
     width: usize,
@@ -71,78 +79,58 @@ impl Pattern {
         }
     }
 
-    fn find_mirror(&self) -> Mirror {
-        Self::single(Self::find_mirrors(self))
-    }
-
-    fn find_mirrors(&self) -> Vec<Mirror> {
-        let mut mirrors = Vec::new();
-
-        for y in 1..self.height {
-            if Self::is_reflection(self, y, &Align::Horizontal) {
-                mirrors.push(Mirror {
-                    location: y,
-                    alignment: Align::Horizontal,
-                });
-            }
-        }
-
-        for x in 1..self.width {
-            if Self::is_reflection(self, x, &Align::Vertical) {
-                mirrors.push(Mirror {
-                    location: x,
-                    alignment: Align::Vertical,
-                });
-            }
-        }
-
-        mirrors
+    /// Finds all mirrors for which the two sides of the fold differ in exactly `smudges` tiles.
+    fn find_mirrors(&self, smudges: usize) -> Vec<Reflection> {
+        let horizontal = (1..self.height).map(|y| Mirror {
+            location: y,
+            alignment: Align::Horizontal,
+        });
+        let vertical = (1..self.width).map(|x| Mirror {
+            location: x,
+            alignment: Align::Vertical,
+        });
+
+        horizontal
+            .chain(vertical)
+            .filter_map(|mirror| {
+                let differences = self.differences(&mirror, smudges);
+
+                (differences.len() == smudges).then_some(Reflection {
+                    mirror,
+                    smudges: differences,
+                })
+            })
+            .collect()
     }
 
-    fn single<T: Copy>(vec: Vec<T>) -> T {
+    fn single<T>(mut vec: Vec<T>) -> T {
         if vec.len() != 1 {
             panic!("Expected 1 element in Vec but received {}", vec.len());
         }
 
-        vec[0]
+        vec.remove(0)
     }
 
-    fn is_reflection(&self, location: usize, alignment: &Align) -> bool {
-        let delta = match alignment {
-            Align::Horizontal => min(location, self.height - location),
-            Align::Vertical => min(location, self.width - location),
+    /// The tiles, formatted (x, y), before the mirror that differ from their reflection after the mirror.
+    ///
+    /// Stops looking as soon as more than `limit` differences are found.
+    fn differences(&self, mirror: &Mirror, limit: usize) -> Vec<(usize, usize)> {
+        let location = mirror.location;
+        let (delta, length) = match mirror.alignment {
+            Align::Horizontal => (min(location, self.height - location), self.width),
+            Align::Vertical => (min(location, self.width - location), self.height),
         };
 
-        (0..delta).all(|d| Self::equals(self, location - 1 - d, location + d, alignment))
-    }
-
-    fn equals(&self, a: usize, b: usize, alignment: &Align) -> bool {
-        match alignment {
-            Align::Horizontal => (0..self.width).all(|x| self.tiles[a][x] == self.tiles[b][x]),
-            Align::Vertical => (0..self.height).all(|y| self.tiles[y][a] == self.tiles[y][b]),
-        }
-    }
-
-    fn repair_smudge(&self) -> Mirror {
-        let original = self.find_mirror();
-        let repaired = (0..self.height)
-            .flat_map(|y| (0..self.width).map(move |x| (x, y)))
-            .flat_map(|t| Self::smudge(self, t.0, t.1))
-            .unique()
-            .filter(|&m| m != original)
-            .collect_vec();
-
-        Self::single(repaired)
-    }
-
-    fn smudge(&self, x: usize, y: usize) -> Vec<Mirror> {
-        let mut smudged = self.clone();
-        smudged.tiles[y][x] = match smudged.tiles[y][x] {
-            Tile::Ash => Tile::Rocks,
-            Tile::Rocks => Tile::Ash,
-        };
-
-        smudged.find_mirrors()
+        (0..delta)
+            .flat_map(|d| (0..length).map(move |i| (location - 1 - d, location + d, i)))
+            .map(|(a, b, i)| match mirror.alignment {
+                Align::Horizontal => ((i, a), (i, b)),
+                Align::Vertical => ((a, i), (b, i)),
+            })
+            .filter(|&(a, b)| self.tiles[a.1][a.0] != self.tiles[b.1][b.0])
+            .map(|(a, _)| a)
+            .take(limit + 1)
+            .collect()
     }
 }
 
@@ -152,14 +140,23 @@ enum Tile {
     Rocks,
 }
 
+/// A mirror, along with the smudges that had to be repaired for it to appear.
+///
+/// Each smudge is reported on the top or left side of the mirror, repairing its reflection instead works just as well.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct Reflection {
+    pub mirror: Mirror,
+    pub smudges: Vec<(usize, usize)>,
+}
+
 #[derive(Debug, Eq, PartialEq, Copy, Clone, Hash)]
-struct Mirror {
-    location: usize,
-    alignment: Align,
+pub struct Mirror {
+    pub location: usize,
+    pub alignment: Align,
 }
 
 #[derive(Debug, Eq, PartialEq, Copy, Clone, Hash)]
-enum Align {
+pub enum Align {
     Horizontal,
     Vertical,
 }
@@ -217,6 +214,62 @@ mod tests {
         assert_eq!(400, solve_2(sample));
     }
 
+    #[test]
+    fn day_13_smudges() {
+        let sample = "#.##..##.\n\
+            ..#.##.#.\n\
+            ##......#\n\
+            ##......#\n\
+            ..#.##.#.\n\
+            ..##..##.\n\
+            #.#.##.#.\n\
+            \n\
+            #...##..#\n\
+            #....#..#\n\
+            ..##..###\n\
+            #####.##.\n\
+            #####.##.\n\
+            ..##..###\n\
+            #....#..#";
+
+        assert_eq!(
+            vec![
+                Reflection {
+                    mirror: Mirror {
+                        location: 3,
+                        alignment: Align::Horizontal
+                    },
+                    smudges: vec![(0, 0)]
+                },
+                Reflection {
+                    mirror: Mirror {
+                        location: 1,
+                        alignment: Align::Horizontal
+                    },
+                    smudges: vec![(4, 0)]
+                },
+            ],
+            reflections(sample, 1)
+        );
+
+        let pattern = Pattern::new(
+            "#.##..##.\n..#.##.#.\n##......#\n##......#\n..#.##.#.\n..##..##.\n#.#.##.#.",
+        );
+        assert_eq!(1, pattern.find_mirrors(0).len());
+        assert_eq!(
+            vec![Mirror {
+                location: 1,
+                alignment: Align::Vertical
+            }],
+            pattern
+                .find_mirrors(2)
+                .iter()
+                .filter(|r| r.mirror.alignment == Align::Vertical)
+                .map(|r| r.mirror)
+                .collect_vec()
+        );
+    }
+
     #[test]
     fn day_13_part_02_solution() {
         let input = include_str!("../../inputs/day_13.txt");
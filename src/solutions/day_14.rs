@@ -1,5 +1,6 @@
-use std::ops::Not;
+use std::fmt::{Display, Formatter};
 
+use itertools::Itertools;
 use rustc_hash::FxHashMap;
 
 pub fn solve_1(dish: Vec<&str>) -> usize {
@@ -7,165 +8,251 @@ pub fn solve_1(dish: Vec<&str>) -> usize {
 }
 
 pub fn solve_2(dish: Vec<&str>) -> usize {
-    Dish::new(dish).cycle().total_load()
+    load_series(dish, "NWSE").load_after(1_000_000_000)
 }
 
-#[derive(Debug, Eq, PartialEq, Clone, Hash)]
+/// Runs the spin program (a sequence of tilts, e.g. "NWSE") until the dish starts repeating itself.
+pub fn load_series(dish: Vec<&str>, program: &str) -> LoadSeries {
+    let program = program.chars().map(Direction::new).collect_vec();
+
+    Dish::new(dish).load_series(&program)
+}
+
+/// The load on the north support beams after every cycle of a spin program.
+///
+/// After an initial `prefix` cycles the dish keeps repeating the same `period` states,
+/// which allows looking up the load after any number of cycles.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct LoadSeries {
+    loads: Vec<usize>,
+    prefix: usize,
+    period: usize,
+}
+
+impl LoadSeries {
+    /// The loads after 0, 1, 2, ... cycles, up to the point the dish returns to an earlier state.
+    pub fn loads(&self) -> &[usize] {
+        &self.loads
+    }
+
+    pub fn prefix(&self) -> usize {
+        self.prefix
+    }
+
+    pub fn period(&self) -> usize {
+        self.period
+    }
+
+    pub fn load_after(&self, cycles: usize) -> usize {
+        if cycles < self.prefix {
+            self.loads[cycles]
+        } else {
+            self.loads[self.prefix + (cycles - self.prefix) % self.period]
+        }
+    }
+
+    /// The loads after 1 up to and including the given number of cycles.
+    pub fn take(&self, cycles: usize) -> Vec<usize> {
+        (1..=cycles).map(|c| self.load_after(c)).collect()
+    }
+}
+
+/// The dish is stored as bitboards, with one `u128` per row, and bit x set if column x holds a rock of that kind.
+///
+/// Tilting north or south lets all rocks in a row move up or down together, one row at a time.
+/// Tilting east or west counts the rounded rocks in between every pair of cube rocks,
+/// and stacks them against the side of that segment in a single operation.
+#[derive(Debug, Eq, PartialEq, Clone)]
 struct Dish {
-    tiles: Vec<Vec<Tile>>,
+    rounded: Vec<u128>,
+    cubes: Vec<u128>,
+    segments: Vec<Vec<Segment>>,
     height: usize,
     width: usize,
 }
 
+/// Stretch of a row without any cube rocks.
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+struct Segment {
+    start: usize,
+    end: usize,
+    mask: u128,
+}
+
 impl Dish {
     fn new(dish: Vec<&str>) -> Dish {
-        let tiles: Vec<Vec<Tile>> = dish
+        let height = dish.len();
+        let width = dish[0].len();
+        if width > u128::BITS as usize {
+            panic!("Dish too wide to fit into a bitboard: {width}");
+        }
+
+        let mut rounded = vec![0; height];
+        let mut cubes = vec![0; height];
+        for (y, &row) in dish.iter().enumerate() {
+            for (x, c) in row.chars().enumerate() {
+                match c {
+                    'O' => rounded[y] |= 1 << x,
+                    '#' => cubes[y] |= 1 << x,
+                    '.' => {}
+                    _ => panic!("Invalid tile: {c}"),
+                }
+            }
+        }
+
+        let segments = cubes
             .iter()
-            .map(|&s| {
-                s.chars()
-                    .map(|c| match c {
-                        'O' => Tile::Rounded,
-                        '#' => Tile::Cube,
-                        '.' => Tile::Empty,
-                        _ => panic!("Invalid tile: {c}"),
-                    })
-                    .collect()
-            })
+            .map(|&row| Self::find_segments(row, width))
             .collect();
-        let height = tiles.len();
-        let width = tiles[0].len();
 
         Self {
-            tiles,
+            rounded,
+            cubes,
+            segments,
             height,
             width,
         }
     }
 
+    fn find_segments(cubes: u128, width: usize) -> Vec<Segment> {
+        (0..width).filter(|&x| cubes & 1 << x == 0).fold(
+            Vec::new(),
+            |mut segments: Vec<Segment>, x| {
+                match segments.last_mut() {
+                    Some(s) if s.end == x => {
+                        s.end += 1;
+                        s.mask |= 1 << x;
+                    }
+                    _ => segments.push(Segment {
+                        start: x,
+                        end: x + 1,
+                        mask: 1 << x,
+                    }),
+                }
+                segments
+            },
+        )
+    }
+
     fn tilt(&mut self, direction: &Direction) -> &Self {
-        let coords: Vec<(usize, usize)> = match direction {
-            Direction::North => (0..self.height)
-                .flat_map(|y| (0..self.width).map(move |x| (x, y)))
-                .collect(),
-            Direction::East => (0..self.width)
-                .rev()
-                .flat_map(|x| (0..self.height).map(move |y| (x, y)))
-                .collect(),
-            Direction::South => (0..self.height)
-                .rev()
-                .flat_map(|y| (0..self.width).map(move |x| (x, y)))
-                .collect(),
-            Direction::West => (0..self.width)
-                .flat_map(|x| (0..self.height).map(move |y| (x, y)))
-                .collect(),
-        };
-
-        for (x, y) in coords {
-            if matches!(self.tiles[y][x], Tile::Rounded) {
-                let new = Self::roll_single(self, (x, y), direction);
-
-                self.tiles[y][x] = Tile::Empty;
-                self.tiles[new.1][new.0] = Tile::Rounded;
+        match direction {
+            Direction::North => {
+                for y in 1..self.height {
+                    self.roll_up(y)
+                }
+            }
+            Direction::South => {
+                for y in (0..self.height - 1).rev() {
+                    self.roll_down(y)
+                }
+            }
+            Direction::East | Direction::West => {
+                for y in 0..self.height {
+                    self.roll_sideways(y, direction)
+                }
             }
         }
 
         self
     }
 
-    fn roll_single(&self, mut coord: (usize, usize), direction: &Direction) -> (usize, usize) {
-        loop {
-            match direction {
-                Direction::North => {
-                    if coord.1 == 0 || matches!(self.tiles[coord.1 - 1][coord.0], Tile::Empty).not()
-                    {
-                        return coord;
-                    }
-                    coord.1 -= 1;
-                }
-                Direction::East => {
-                    if coord.0 == self.width - 1
-                        || matches!(self.tiles[coord.1][coord.0 + 1], Tile::Empty).not()
-                    {
-                        return coord;
-                    }
-                    coord.0 += 1;
-                }
-                Direction::South => {
-                    if coord.1 == self.height - 1
-                        || matches!(self.tiles[coord.1 + 1][coord.0], Tile::Empty).not()
-                    {
-                        return coord;
-                    }
-                    coord.1 += 1;
-                }
-                Direction::West => {
-                    if coord.0 == 0 || matches!(self.tiles[coord.1][coord.0 - 1], Tile::Empty).not()
-                    {
-                        return coord;
-                    }
-                    coord.0 -= 1;
-                }
-            }
+    /// Moves the rocks in the row up as far as they go, assuming all rows above it are tilted already.
+    fn roll_up(&mut self, mut y: usize) {
+        let mut moving = self.rounded[y];
+
+        while y > 0 && moving != 0 {
+            moving &= !(self.rounded[y - 1] | self.cubes[y - 1]);
+            self.rounded[y] &= !moving;
+            self.rounded[y - 1] |= moving;
+            y -= 1;
         }
     }
 
-    fn total_load(&self) -> usize {
-        (0..self.height)
-            .map(|y| {
-                self.tiles[y]
-                    .iter()
-                    .filter(|&t| matches!(t, Tile::Rounded))
-                    .count()
-                    * (self.height - y)
-            })
-            .sum()
+    /// Moves the rocks in the row down as far as they go, assuming all rows below it are tilted already.
+    fn roll_down(&mut self, mut y: usize) {
+        let mut moving = self.rounded[y];
+
+        while y < self.height - 1 && moving != 0 {
+            moving &= !(self.rounded[y + 1] | self.cubes[y + 1]);
+            self.rounded[y] &= !moving;
+            self.rounded[y + 1] |= moving;
+            y += 1;
+        }
     }
 
-    fn cycle(&mut self) -> &Self {
-        let (repeat_start, repeat_length) = Self::detect_repeat(&mut self.clone());
+    fn roll_sideways(&mut self, y: usize, direction: &Direction) {
+        let row = self.rounded[y];
 
-        let nr_cycles = (1_000_000_000 - repeat_start) % repeat_length + repeat_start;
+        self.rounded[y] = self.segments[y]
+            .iter()
+            .map(|s| {
+                let count = (row & s.mask).count_ones() as usize;
+                match direction {
+                    Direction::West => Self::low_bits(count) << s.start,
+                    _ => Self::low_bits(count) << (s.end - count),
+                }
+            })
+            .fold(0, |acc, bits| acc | bits);
+    }
 
-        for _ in 0..nr_cycles {
-            self.tilt(&Direction::North);
-            self.tilt(&Direction::West);
-            self.tilt(&Direction::South);
-            self.tilt(&Direction::East);
+    fn low_bits(count: usize) -> u128 {
+        match count {
+            128 => u128::MAX,
+            _ => (1 << count) - 1,
         }
-
-        self
     }
 
-    fn detect_repeat(&mut self) -> (usize, usize) {
-        let mut memo: FxHashMap<Self, _> = FxHashMap::default();
+    fn total_load(&self) -> usize {
+        self.rounded
+            .iter()
+            .enumerate()
+            .map(|(y, row)| row.count_ones() as usize * (self.height - y))
+            .sum()
+    }
 
-        let mut repeat_start: usize = 0;
-        let mut repeat_length: usize = 0;
+    fn load_series(&mut self, program: &[Direction]) -> LoadSeries {
+        // Only the rounded rocks move, so their bitboard is all the state there is
+        let mut memo: FxHashMap<Vec<u128>, usize> = FxHashMap::default();
+        let mut loads = Vec::new();
 
-        for cycle in 0..usize::MAX {
-            if memo.contains_key(self) {
-                repeat_start = memo[&self];
-                repeat_length = cycle - repeat_start;
-                break;
-            } else {
-                memo.insert(self.clone(), cycle);
+        loop {
+            if let Some(&prefix) = memo.get(&self.rounded) {
+                let period = loads.len() - prefix;
+                return LoadSeries {
+                    loads,
+                    prefix,
+                    period,
+                };
             }
 
-            self.tilt(&Direction::North);
-            self.tilt(&Direction::West);
-            self.tilt(&Direction::South);
-            self.tilt(&Direction::East);
-        }
+            memo.insert(self.rounded.clone(), loads.len());
+            loads.push(self.total_load());
 
-        (repeat_start, repeat_length)
+            program.iter().for_each(|d| {
+                self.tilt(d);
+            });
+        }
     }
 }
 
-#[derive(Debug, Eq, PartialEq, Copy, Clone, Hash)]
-enum Tile {
-    Rounded,
-    Cube,
-    Empty,
+impl Display for Dish {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let dish = (0..self.height)
+            .map(|y| {
+                (0..self.width)
+                    .map(
+                        |x| match (self.rounded[y] >> x & 1, self.cubes[y] >> x & 1) {
+                            (1, _) => 'O',
+                            (_, 1) => '#',
+                            _ => '.',
+                        },
+                    )
+                    .collect::<String>()
+            })
+            .join("\n");
+
+        write!(f, "{}", dish)
+    }
 }
 
 #[derive(Debug, Eq, PartialEq, Copy, Clone, Hash)]
@@ -176,6 +263,18 @@ enum Direction {
     West,
 }
 
+impl Direction {
+    fn new(direction: char) -> Direction {
+        match direction {
+            'N' => Direction::North,
+            'E' => Direction::East,
+            'S' => Direction::South,
+            'W' => Direction::West,
+            _ => panic!("Invalid direction: {direction}"),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -223,6 +322,88 @@ mod tests {
         assert_eq!(64, solve_2(sample));
     }
 
+    #[test]
+    fn day_14_load_series() {
+        let sample = vec![
+            "O....#....",
+            "O.OO#....#",
+            ".....##...",
+            "OO.#O....O",
+            ".O.....O#.",
+            "O.#..O.#.#",
+            "..O..#O..O",
+            ".......O..",
+            "#....###..",
+            "#OO..#....",
+        ];
+
+        let series = load_series(sample.clone(), "NWSE");
+        assert_eq!(3, series.prefix());
+        assert_eq!(7, series.period());
+        assert_eq!(vec![87, 69, 69, 69, 65, 64, 65, 63], series.take(8));
+        assert_eq!(series.load_after(3), series.load_after(3 + 7 * 1_000));
+        assert_eq!(64, series.load_after(1_000_000_000));
+
+        // Tilting north over and over again changes nothing after the first cycle
+        let series = load_series(sample.clone(), "N");
+        assert_eq!(vec![104, 136], series.loads());
+        assert_eq!((1, 1), (series.prefix(), series.period()));
+        assert_eq!(136, series.load_after(42));
+
+        let series = load_series(sample, "");
+        assert_eq!((0, 1), (series.prefix(), series.period()));
+        assert_eq!(104, series.load_after(42));
+    }
+
+    #[test]
+    fn day_14_tilt() {
+        let sample = vec![
+            "O....#....",
+            "O.OO#....#",
+            ".....##...",
+            "OO.#O....O",
+            ".O.....O#.",
+            "O.#..O.#.#",
+            "..O..#O..O",
+            ".......O..",
+            "#....###..",
+            "#OO..#....",
+        ];
+        let mut dish = Dish::new(sample);
+
+        dish.tilt(&Direction::North);
+        assert_eq!(
+            "OOOO.#.O..\n\
+             OO..#....#\n\
+             OO..O##..O\n\
+             O..#.OO...\n\
+             ........#.\n\
+             ..#....#.#\n\
+             ..O..#.O.O\n\
+             ..O.......\n\
+             #....###..\n\
+             #....#....",
+            dish.to_string()
+        );
+
+        dish.tilt(&Direction::West);
+        dish.tilt(&Direction::South);
+        dish.tilt(&Direction::East);
+        assert_eq!(
+            ".....#....\n\
+             ....#...O#\n\
+             ...OO##...\n\
+             .OO#......\n\
+             .....OOO#.\n\
+             .O#...O#.#\n\
+             ....O#....\n\
+             ......OOOO\n\
+             #...O###..\n\
+             #..OO#....",
+            dish.to_string()
+        );
+    }
+
     #[test]
     fn day_14_part_02_solution() {
         let input = include_str!("../../inputs/day_14.txt").lines().collect();
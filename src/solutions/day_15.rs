@@ -1,7 +1,9 @@
+use std::fmt::{Display, Formatter};
+use std::io::{BufReader, Bytes, Read};
 use std::str::FromStr;
-use std::usize;
 
 use indexmap::IndexMap;
+use itertools::Itertools;
 
 pub fn solve_1(init_sequence: &str) -> usize {
     init_sequence.split(',').map(|s| hash(s) as usize).sum()
@@ -17,16 +19,106 @@ pub fn solve_2(init_sequence: &str) -> usize {
     facility.focus_power()
 }
 
-fn hash(step: &str) -> u8 {
-    let mut value = 0;
+/// The HASH algorithm, skipping newlines just like [`hash_steps`] does.
+pub fn hash(step: &str) -> u8 {
+    step.bytes().filter(|&b| b != b'\n').fold(0, hash_byte)
+}
+
+fn hash_byte(value: u8, byte: u8) -> u8 {
+    ((value as u16 + byte as u16) * 17 % 256) as u8
+}
+
+/// Hashes every step of an initialization sequence while reading it, without holding the whole sequence in memory.
+///
+/// Newlines are ignored, as instructed by the puzzle.
+pub fn hash_steps<R: Read>(reader: R) -> StepHashes<R> {
+    StepHashes {
+        bytes: BufReader::new(reader).bytes(),
+        done: false,
+    }
+}
+
+pub struct StepHashes<R: Read> {
+    bytes: Bytes<BufReader<R>>,
+    done: bool,
+}
+
+impl<R: Read> Iterator for StepHashes<R> {
+    type Item = std::io::Result<u8>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        let mut value = 0;
+        let mut started = false;
+        loop {
+            match self.bytes.next() {
+                Some(Ok(b',')) => return Some(Ok(value)),
+                Some(Ok(b'\n')) => {}
+                Some(Ok(byte)) => {
+                    value = hash_byte(value, byte);
+                    started = true;
+                }
+                Some(Err(e)) => return Some(Err(e)),
+                None => {
+                    self.done = true;
+                    // Nothing after the last comma (or no input at all) is not a step
+                    return started.then_some(Ok(value));
+                }
+            }
+        }
+    }
+}
+
+/// Steps through the initialization sequence, one step at a time.
+#[derive(Debug)]
+pub struct Debugger<'a> {
+    steps: Vec<&'a str>,
+    executed: usize,
+    facility: Facility<'a>,
+}
+
+impl<'a> Debugger<'a> {
+    pub fn new(init_sequence: &'a str) -> Debugger<'a> {
+        Debugger {
+            steps: init_sequence.split(',').collect(),
+            executed: 0,
+            facility: Facility::new(),
+        }
+    }
+
+    /// Performs the next step, returning a record of the boxes afterwards, or `None` if all steps were performed.
+    pub fn step(&mut self) -> Option<String> {
+        let step = *self.steps.get(self.executed)?;
+        self.facility.install(Step::new(step));
+        self.executed += 1;
+
+        Some(format!("After \"{}\":\n{}", step, self.facility))
+    }
+
+    /// Starts over, performing only the first `nr_steps` steps, returning the state of the boxes afterwards.
+    pub fn replay(&mut self, nr_steps: usize) -> String {
+        self.facility = Facility::new();
+        self.executed = 0;
+        (0..nr_steps).for_each(|_| {
+            self.step();
+        });
 
-    for x in step.chars() {
-        value += x as u16;
-        value *= 17;
-        value %= 256;
+        self.facility.to_string()
     }
 
-    value as u8
+    pub fn focus_power(&self) -> usize {
+        self.facility.focus_power()
+    }
+}
+
+/// Records the state of the boxes after every step of the initialization sequence.
+pub fn trace(init_sequence: &str) -> Vec<String> {
+    let mut debugger = Debugger::new(init_sequence);
+
+    std::iter::from_fn(|| debugger.step()).collect()
 }
 
 #[derive(Debug)]
@@ -74,6 +166,26 @@ impl<'a> Facility<'a> {
     }
 }
 
+impl<'a> Display for Facility<'a> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let boxes = self
+            .boxes
+            .iter()
+            .enumerate()
+            .filter(|(_, lenses)| !lenses.is_empty())
+            .map(|(box_nr, lenses)| {
+                let lenses = lenses
+                    .iter()
+                    .map(|(label, focal_length)| format!("[{} {}]", label, focal_length))
+                    .join(" ");
+                format!("Box {}: {}\n", box_nr, lenses)
+            })
+            .join("");
+
+        write!(f, "{}", boxes)
+    }
+}
+
 #[derive(Debug)]
 enum Step<'a> {
     Add { lens: Lens<'a> },
@@ -129,6 +241,61 @@ mod tests {
         assert_eq!(145, solve_2(sample));
     }
 
+    #[test]
+    fn day_15_hash_steps() {
+        let sample = "rn=1,cm-,qp=3,cm=2,qp-,pc=4,\not=9,ab=5,pc-,pc=6,ot=7\n";
+        let hashes = hash_steps(sample.as_bytes())
+            .map(|h| h.unwrap())
+            .collect_vec();
+
+        assert_eq!(vec![30, 253, 97, 47, 14, 180, 9, 197, 48, 214, 231], hashes);
+        assert_eq!(52, hash("HASH"));
+
+        let hashes = |input: &str| {
+            hash_steps(input.as_bytes())
+                .map(|h| h.unwrap())
+                .collect_vec()
+        };
+        assert!(hashes("").is_empty());
+        assert!(hashes("\n").is_empty());
+        assert_eq!(vec![52], hashes("HASH,"));
+        assert_eq!(vec![52], hashes("HASH,\n"));
+
+        let sample = "rn=1,cm-,qp=3,cm=2,qp-,pc=4,ot=9,ab=5,pc-,pc=6,ot=7\n";
+        assert_eq!(52, hash("HASH\n"));
+        assert_eq!(1_320, solve_1(sample));
+        assert_eq!(
+            1_320,
+            hash_steps(sample.as_bytes())
+                .map(|h| h.unwrap() as usize)
+                .sum::<usize>()
+        );
+    }
+
+    #[test]
+    fn day_15_debugger() {
+        let sample = "rn=1,cm-,qp=3,cm=2,qp-,pc=4,ot=9,ab=5,pc-,pc=6,ot=7";
+        let trace = trace(sample);
+
+        assert_eq!(11, trace.len());
+        assert_eq!("After \"rn=1\":\nBox 0: [rn 1]\n", trace[0]);
+        assert_eq!(
+            "After \"ot=7\":\nBox 0: [rn 1] [cm 2]\nBox 3: [ot 7] [ab 5] [pc 6]\n",
+            trace[10]
+        );
+
+        let mut debugger = Debugger::new(sample);
+        assert_eq!("Box 0: [rn 1] [cm 2]\nBox 1: [qp 3]\n", debugger.replay(4));
+        assert_eq!(
+            Some("After \"qp-\":\nBox 0: [rn 1] [cm 2]\n".to_string()),
+            debugger.step()
+        );
+        assert_eq!("", debugger.replay(0));
+        debugger.replay(11);
+        assert_eq!(145, debugger.focus_power());
+        assert_eq!(None, debugger.step());
+    }
+
     #[test]
     fn day_15_part_02_solution() {
         let input = include_str!("../../inputs/day_15.txt");
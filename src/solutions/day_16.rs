@@ -5,7 +5,7 @@ use itertools::Itertools;
 use rayon::prelude::*;
 use rustc_hash::{FxHashMap, FxHashSet};
 
-type Coord = (i16, i16);
+pub type Coord = (i16, i16);
 
 pub fn solve_1(layout: Vec<&str>) -> u32 {
     Layout::new(layout).energized_count_single()
@@ -16,14 +16,14 @@ pub fn solve_2(layout: Vec<&str>) -> u32 {
 }
 
 #[derive(Debug)]
-struct Layout {
+pub struct Layout {
     grid: FxHashMap<Coord, Tile>,
     height: i16,
     width: i16,
 }
 
 impl Layout {
-    fn new(layout: Vec<&str>) -> Layout {
+    pub fn new(layout: Vec<&str>) -> Layout {
         let grid: FxHashMap<Coord, Tile> = layout
             .iter()
             .enumerate()
@@ -54,10 +54,21 @@ impl Layout {
     }
 
     fn energized_count_single(&self) -> u32 {
-        Self::bounce_light(self, ((0, 0), Direction::Right))
+        self.energized((0, 0), Direction::Right).len() as u32
     }
 
     fn energized_count_all(&self) -> u32 {
+        let cache = BeamCache::new(self);
+
+        self.entries()
+            .par_iter()
+            .map(|&(coord, direction)| cache.energized(self, coord, direction).count_ones())
+            .max()
+            .unwrap()
+    }
+
+    /// Every tile along the edge, with the light entering it moving away from that edge.
+    fn entries(&self) -> Vec<(Coord, Direction)> {
         let up_s = (0..self.width)
             .map(|x| ((x, self.height - 1), Direction::Up))
             .collect_vec();
@@ -71,19 +82,15 @@ impl Layout {
             .map(|y| ((self.width - 1, y), Direction::Left))
             .collect_vec();
 
-        [up_s, right_s, down_s, left_s]
-            .par_iter()
-            .flatten()
-            .map(|&init| Self::bounce_light(self, init))
-            .max()
-            .unwrap()
+        [up_s, right_s, down_s, left_s].concat()
     }
 
-    fn bounce_light(&self, init: (Coord, Direction)) -> u32 {
+    /// All tiles energized by light entering at the given tile, traced beam by beam.
+    pub fn energized(&self, start: Coord, direction: Direction) -> FxHashSet<Coord> {
         let mut seen = FxHashSet::default();
         let mut to_visit: VecDeque<(Coord, Direction)> = VecDeque::new();
 
-        to_visit.push_back((init.0, init.1));
+        to_visit.push_back((start, direction));
 
         while let Some((coord, direction)) = to_visit.pop_front() {
             if self.grid.contains_key(&coord).not() || seen.contains(&(coord, direction)) {
@@ -91,62 +98,257 @@ impl Layout {
             }
             seen.insert((coord, direction));
 
-            let tile = self.grid[&coord];
-            match tile {
-                Tile::Empty => match direction {
-                    Direction::Up => Self::push_up(&mut to_visit, coord),
-                    Direction::Right => Self::push_right(&mut to_visit, coord),
-                    Direction::Down => Self::push_down(&mut to_visit, coord),
-                    Direction::Left => Self::push_left(&mut to_visit, coord),
-                },
-                Tile::MirrorLU => match direction {
-                    Direction::Up => Self::push_right(&mut to_visit, coord),
-                    Direction::Right => Self::push_up(&mut to_visit, coord),
-                    Direction::Down => Self::push_left(&mut to_visit, coord),
-                    Direction::Left => Self::push_down(&mut to_visit, coord),
-                },
-                Tile::MirrorLD => match direction {
-                    Direction::Up => Self::push_left(&mut to_visit, coord),
-                    Direction::Right => Self::push_down(&mut to_visit, coord),
-                    Direction::Down => Self::push_right(&mut to_visit, coord),
-                    Direction::Left => Self::push_up(&mut to_visit, coord),
-                },
-                Tile::SplitterUD => match direction {
-                    Direction::Up => Self::push_up(&mut to_visit, coord),
-                    Direction::Down => Self::push_down(&mut to_visit, coord),
-                    Direction::Right | Direction::Left => {
-                        Self::push_up(&mut to_visit, coord);
-                        Self::push_down(&mut to_visit, coord);
-                    }
-                },
-                Tile::SplitterLR => match direction {
-                    Direction::Right => Self::push_right(&mut to_visit, coord),
-                    Direction::Left => Self::push_left(&mut to_visit, coord),
-                    Direction::Up | Direction::Down => {
-                        Self::push_right(&mut to_visit, coord);
-                        Self::push_left(&mut to_visit, coord);
+            self.grid[&coord]
+                .bounce(direction)
+                .into_iter()
+                .for_each(|d| to_visit.push_back((d.step(coord), d)));
+        }
+
+        seen.iter().map(|&(coord, _)| coord).collect()
+    }
+
+    /// Draws the layout with every energized tile shown as "#".
+    pub fn render(&self, energized: &FxHashSet<Coord>) -> String {
+        (0..self.height)
+            .map(|y| {
+                (0..self.width)
+                    .map(|x| match energized.contains(&(x, y)) {
+                        true => '#',
+                        false => '.',
+                    })
+                    .collect::<String>()
+            })
+            .join("\n")
+    }
+
+    /// Draws the layout with every tile showing how often it gets energized, over all entries along the edges.
+    ///
+    /// The counts are scaled to a single digit, "9" being the tile energized most often, "0" one never energized.
+    pub fn heatmap(&self) -> String {
+        let cache = BeamCache::new(self);
+        let mut counts = vec![0usize; (self.width * self.height) as usize];
+
+        for (coord, direction) in self.entries() {
+            let energized = cache.energized(self, coord, direction);
+            counts
+                .iter_mut()
+                .enumerate()
+                .filter(|(idx, _)| energized.contains(*idx))
+                .for_each(|(_, c)| *c += 1);
+        }
+
+        let max = counts.iter().max().copied().unwrap_or(0).max(1);
+        counts
+            .chunks(self.width as usize)
+            .map(|row| {
+                row.iter()
+                    .map(|&c| char::from_digit((c * 9).div_ceil(max) as u32, 10).unwrap())
+                    .collect::<String>()
+            })
+            .join("\n")
+    }
+
+    fn index(&self, coord: Coord) -> usize {
+        (coord.1 * self.width + coord.0) as usize
+    }
+}
+
+/// Beams only ever split at splitters, which always send out the same 2 beams, no matter which side they are hit from.
+///
+/// The light is therefore traced as segments, each running from a splitter until it hits the next splitter.
+/// The splitters and these segments form a graph, which is condensed into its strongly connected components.
+/// Walking the components in reverse topological order, every splitter gets the full set of tiles it energizes,
+/// after which light entering anywhere only needs tracing up to the first splitter it hits.
+#[derive(Debug)]
+struct BeamCache {
+    splitters: FxHashMap<Coord, usize>,
+    /// For every splitter, all tiles energized once it is hit
+    reach: Vec<TileSet>,
+}
+
+impl BeamCache {
+    fn new(layout: &Layout) -> BeamCache {
+        let coords = layout
+            .grid
+            .iter()
+            .filter(|(_, t)| matches!(t, Tile::SplitterLR | Tile::SplitterUD))
+            .map(|(&c, _)| c)
+            .sorted()
+            .collect_vec();
+        let splitters: FxHashMap<Coord, usize> =
+            coords.iter().enumerate().map(|(i, &c)| (c, i)).collect();
+
+        // Trace the 2 beams coming out of every splitter, up to the next splitter they hit
+        let mut own_tiles = Vec::new();
+        let mut edges = Vec::new();
+        for &coord in &coords {
+            let mut tiles = TileSet::new(layout);
+            tiles.insert(layout.index(coord));
+            let mut next = Vec::new();
+
+            let outputs = match layout.grid[&coord] {
+                Tile::SplitterLR => [Direction::Left, Direction::Right],
+                _ => [Direction::Up, Direction::Down],
+            };
+            for direction in outputs {
+                let (segment, hit) = Self::trace_segment(layout, direction.step(coord), direction);
+                tiles.union(&segment);
+                next.extend(hit.map(|h| splitters[&h]));
+            }
+
+            own_tiles.push(tiles);
+            edges.push(next);
+        }
+
+        // Tarjan returns the components in reverse topological order, so successors are always complete
+        let mut reach: Vec<TileSet> = vec![TileSet::new(layout); coords.len()];
+        for component in Self::tarjan(&edges) {
+            let mut tiles = TileSet::new(layout);
+            for &splitter in &component {
+                tiles.union(&own_tiles[splitter]);
+                for &next in &edges[splitter] {
+                    if component.contains(&next).not() {
+                        tiles.union(&reach[next]);
                     }
-                },
+                }
+            }
+            component.iter().for_each(|&s| reach[s] = tiles.clone());
+        }
+
+        BeamCache { splitters, reach }
+    }
+
+    fn energized(&self, layout: &Layout, start: Coord, direction: Direction) -> TileSet {
+        let (mut tiles, hit) = Self::trace_segment(layout, start, direction);
+        if let Some(splitter) = hit {
+            tiles.union(&self.reach[self.splitters[&splitter]]);
+        }
+
+        tiles
+    }
+
+    /// Follows a single beam until it leaves the layout, runs in a loop, or hits a splitter side-on (which is returned).
+    fn trace_segment(
+        layout: &Layout,
+        mut coord: Coord,
+        mut direction: Direction,
+    ) -> (TileSet, Option<Coord>) {
+        let mut tiles = TileSet::new(layout);
+        let mut seen = FxHashSet::default();
+
+        while let Some(&tile) = layout.grid.get(&coord) {
+            if seen.insert((coord, direction)).not() {
+                break;
+            }
+            tiles.insert(layout.index(coord));
+
+            match tile.bounce(direction)[..] {
+                [next] => direction = next,
+                _ => return (tiles, Some(coord)),
+            }
+            coord = direction.step(coord);
+        }
+
+        (tiles, None)
+    }
+
+    fn tarjan(edges: &[Vec<usize>]) -> Vec<Vec<usize>> {
+        let mut state = Tarjan {
+            index: vec![None; edges.len()],
+            low_link: vec![0; edges.len()],
+            on_stack: vec![false; edges.len()],
+            stack: Vec::new(),
+            next_index: 0,
+            components: Vec::new(),
+        };
+
+        for node in 0..edges.len() {
+            if state.index[node].is_none() {
+                state.connect(edges, node);
+            }
+        }
+
+        state.components
+    }
+}
+
+struct Tarjan {
+    index: Vec<Option<usize>>,
+    low_link: Vec<usize>,
+    on_stack: Vec<bool>,
+    stack: Vec<usize>,
+    next_index: usize,
+    components: Vec<Vec<usize>>,
+}
+
+impl Tarjan {
+    fn connect(&mut self, edges: &[Vec<usize>], node: usize) {
+        self.index[node] = Some(self.next_index);
+        self.low_link[node] = self.next_index;
+        self.next_index += 1;
+        self.stack.push(node);
+        self.on_stack[node] = true;
+
+        for &next in &edges[node] {
+            match self.index[next] {
+                None => {
+                    self.connect(edges, next);
+                    self.low_link[node] = self.low_link[node].min(self.low_link[next]);
+                }
+                Some(index) if self.on_stack[next] => {
+                    self.low_link[node] = self.low_link[node].min(index);
+                }
+                Some(_) => {}
             }
         }
 
-        seen.iter().map(|((x, y), _)| (x, y)).unique().count() as u32
+        if Some(self.low_link[node]) == self.index[node] {
+            let mut component = Vec::new();
+            loop {
+                let member = self.stack.pop().unwrap();
+                self.on_stack[member] = false;
+                component.push(member);
+                if member == node {
+                    break;
+                }
+            }
+            self.components.push(component);
+        }
     }
+}
+
+/// Set of tiles, with every tile being a single bit.
+#[derive(Debug, Clone)]
+struct TileSet {
+    bits: Vec<u64>,
+}
 
-    fn push_up(to_visit: &mut VecDeque<(Coord, Direction)>, coord: Coord) {
-        to_visit.push_back(((coord.0, coord.1 - 1), Direction::Up))
+impl TileSet {
+    fn new(layout: &Layout) -> TileSet {
+        let nr_tiles = (layout.width * layout.height) as usize;
+
+        TileSet {
+            bits: vec![0; nr_tiles.div_ceil(64)],
+        }
     }
 
-    fn push_right(to_visit: &mut VecDeque<(Coord, Direction)>, coord: Coord) {
-        to_visit.push_back(((coord.0 + 1, coord.1), Direction::Right))
+    fn insert(&mut self, idx: usize) {
+        self.bits[idx / 64] |= 1 << (idx % 64);
     }
 
-    fn push_down(to_visit: &mut VecDeque<(Coord, Direction)>, coord: Coord) {
-        to_visit.push_back(((coord.0, coord.1 + 1), Direction::Down))
+    fn contains(&self, idx: usize) -> bool {
+        self.bits[idx / 64] & 1 << (idx % 64) != 0
     }
 
-    fn push_left(to_visit: &mut VecDeque<(Coord, Direction)>, coord: Coord) {
-        to_visit.push_back(((coord.0 - 1, coord.1), Direction::Left))
+    fn union(&mut self, other: &TileSet) {
+        self.bits
+            .iter_mut()
+            .zip(&other.bits)
+            .for_each(|(a, b)| *a |= b);
+    }
+
+    fn count_ones(&self) -> u32 {
+        self.bits.iter().map(|b| b.count_ones()).sum()
     }
 }
 
@@ -159,14 +361,54 @@ enum Tile {
     SplitterLR,
 }
 
+impl Tile {
+    /// The directions light continues in, after hitting this tile moving in the given direction.
+    fn bounce(&self, direction: Direction) -> Vec<Direction> {
+        match self {
+            Tile::Empty => vec![direction],
+            Tile::MirrorLU => match direction {
+                Direction::Up => vec![Direction::Right],
+                Direction::Right => vec![Direction::Up],
+                Direction::Down => vec![Direction::Left],
+                Direction::Left => vec![Direction::Down],
+            },
+            Tile::MirrorLD => match direction {
+                Direction::Up => vec![Direction::Left],
+                Direction::Right => vec![Direction::Down],
+                Direction::Down => vec![Direction::Right],
+                Direction::Left => vec![Direction::Up],
+            },
+            Tile::SplitterUD => match direction {
+                Direction::Up | Direction::Down => vec![direction],
+                Direction::Right | Direction::Left => vec![Direction::Up, Direction::Down],
+            },
+            Tile::SplitterLR => match direction {
+                Direction::Right | Direction::Left => vec![direction],
+                Direction::Up | Direction::Down => vec![Direction::Right, Direction::Left],
+            },
+        }
+    }
+}
+
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
-enum Direction {
+pub enum Direction {
     Up,
     Right,
     Down,
     Left,
 }
 
+impl Direction {
+    fn step(&self, coord: Coord) -> Coord {
+        match self {
+            Direction::Up => (coord.0, coord.1 - 1),
+            Direction::Right => (coord.0 + 1, coord.1),
+            Direction::Down => (coord.0, coord.1 + 1),
+            Direction::Left => (coord.0 - 1, coord.1),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -214,6 +456,65 @@ mod tests {
         assert_eq!(51, solve_2(sample));
     }
 
+    #[test]
+    fn day_16_render() {
+        let sample = vec![
+            r".|...\....",
+            r"|.-.\.....",
+            r".....|-...",
+            r"........|.",
+            r"..........",
+            r".........\",
+            r"..../.\\..",
+            r".-.-/..|..",
+            r".|....-|.\",
+            r"..//.|....",
+        ];
+        let layout = Layout::new(sample);
+
+        assert_eq!(
+            "######....\n\
+             .#...#....\n\
+             .#...#####\n\
+             .#...##...\n\
+             .#...##...\n\
+             .#...##...\n\
+             .#..####..\n\
+             ########..\n\
+             .#######..\n\
+             .#...#.#..",
+            layout.render(&layout.energized((0, 0), Direction::Right))
+        );
+        assert_eq!(10, layout.heatmap().lines().count());
+        assert!(layout.heatmap().contains('9'));
+    }
+
+    #[test]
+    fn day_16_beam_cache() {
+        let sample = vec![
+            r".|...\....",
+            r"|.-.\.....",
+            r".....|-...",
+            r"........|.",
+            r"..........",
+            r".........\",
+            r"..../.\\..",
+            r".-.-/..|..",
+            r".|....-|.\",
+            r"..//.|....",
+        ];
+        let layout = Layout::new(sample);
+        let cache = BeamCache::new(&layout);
+
+        for (coord, direction) in layout.entries() {
+            let energized = layout.energized(coord, direction);
+            let cached = cache.energized(&layout, coord, direction);
+
+            assert_eq!(energized.len() as u32, cached.count_ones());
+            assert!(energized.iter().all(|&c| cached.contains(layout.index(c))));
+        }
+    }
+
     #[test]
     fn day_16_part_02_solution() {
         let input = include_str!("../../inputs/day_16.txt").lines().collect();
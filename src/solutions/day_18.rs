@@ -1,54 +1,155 @@
+use std::fs::File;
+use std::io::Write;
 use std::str::FromStr;
 
 use itertools::Itertools;
 
-const DELTAS: [(i64, i64); 4] = [(0, -1), (1, 0), (0, 1), (-1, 0)];
+use crate::util::geometry::{Direction, Polygon};
+
+/// The largest side of the rendered SVG image, in pixels
+const SVG_SIZE: f64 = 1_000.0;
+const SVG_MARGIN: f64 = 10.0;
 
 pub fn solve_1(plan: &str) -> i64 {
-    let mut prev = (0, 0);
-    let mut current = (0, 0);
-    let mut perimeter = 0;
-    let mut triangles_sum = 0;
-
-    for instr in plan.lines() {
-        let split = instr.split(' ').collect_vec();
-        let steps = i64::from_str(split[1]).unwrap();
-        let direction = match split[0] {
-            "U" => 0,
-            "R" => 1,
-            "D" => 2,
-            "L" => 3,
-            _ => unreachable!(),
+    DigPlan::new(plan, Encoding::DirectionSteps)
+        .polygon()
+        .lattice_points()
+}
+
+pub fn solve_2(plan: &str) -> i64 {
+    DigPlan::new(plan, Encoding::HexColour)
+        .polygon()
+        .lattice_points()
+}
+
+/// How a dig plan instruction encodes its direction and number of steps.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Encoding {
+    /// The first 2 fields, e.g. "R 6" in "R 6 (#70c710)"
+    DirectionSteps,
+    /// The "colour" field, the first 5 hex digits being the steps, the last one the direction
+    HexColour,
+}
+
+#[derive(Debug)]
+pub struct DigPlan {
+    instructions: Vec<Instruction>,
+}
+
+#[derive(Debug, Clone)]
+struct Instruction {
+    direction: Direction,
+    steps: i64,
+    colour: String,
+}
+
+impl DigPlan {
+    pub fn new(plan: &str, encoding: Encoding) -> DigPlan {
+        let instructions = plan
+            .lines()
+            .map(|l| Instruction::new(l, encoding))
+            .collect();
+
+        DigPlan { instructions }
+    }
+
+    /// The trench dug out by following the plan.
+    pub fn polygon(&self) -> Polygon {
+        Polygon::from_moves(self.instructions.iter().map(|i| (i.direction, i.steps)))
+    }
+
+    /// Renders the lagoon as an SVG image, with its interior filled and every edge of the trench in its own colour.
+    ///
+    /// The image is scaled to fit within 1000 by 1000 pixels, whatever the size of the lagoon.
+    /// An empty plan digs no lagoon, giving an empty image.
+    pub fn svg(&self) -> String {
+        let polygon = self.polygon();
+        let vertices = polygon.vertices();
+
+        let (Some((min_x, max_x)), Some((min_y, max_y))) = (
+            vertices.iter().map(|v| v.0).minmax().into_option(),
+            vertices.iter().map(|v| v.1).minmax().into_option(),
+        ) else {
+            return "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"0\" height=\"0\"/>\n"
+                .to_string();
+        };
+        let scale = SVG_SIZE / (max_x - min_x).max(max_y - min_y).max(1) as f64;
+        let project = |(x, y): (i64, i64)| {
+            (
+                (x - min_x) as f64 * scale + SVG_MARGIN,
+                (y - min_y) as f64 * scale + SVG_MARGIN,
+            )
         };
-        let (d_x, d_y) = DELTAS[direction];
 
-        current = (current.0 + d_x * steps, current.1 + d_y * steps);
-        perimeter += steps;
-        triangles_sum += (prev.1 + current.1) * (prev.0 - current.0);
-        prev = current;
+        let width = (max_x - min_x) as f64 * scale + 2.0 * SVG_MARGIN;
+        let height = (max_y - min_y) as f64 * scale + 2.0 * SVG_MARGIN;
+        let stroke_width = scale.clamp(1.0, SVG_MARGIN);
+
+        let points = vertices
+            .iter()
+            .map(|&v| project(v))
+            .map(|(x, y)| format!("{:.1},{:.1}", x, y))
+            .join(" ");
+
+        // Moving back to the start closes the loop, so every instruction has its own edge
+        let edges = self
+            .instructions
+            .iter()
+            .zip(vertices.iter().zip(vertices.iter().cycle().skip(1)))
+            .map(|(instruction, (&from, &to))| {
+                let (x1, y1) = project(from);
+                let (x2, y2) = project(to);
+                format!(
+                    "  <line x1=\"{:.1}\" y1=\"{:.1}\" x2=\"{:.1}\" y2=\"{:.1}\" stroke=\"{}\" stroke-width=\"{:.1}\" stroke-linecap=\"square\"/>\n",
+                    x1, y1, x2, y2, instruction.colour, stroke_width
+                )
+            })
+            .join("");
+
+        format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{:.0}\" height=\"{:.0}\" viewBox=\"0 0 {:.1} {:.1}\">\n\
+             \x20 <polygon points=\"{}\" fill=\"#d3d3d3\"/>\n\
+             {}\
+             </svg>\n",
+            width, height, width, height, points, edges
+        )
     }
 
-    i64::abs(triangles_sum / 2) + perimeter / 2 + 1
+    pub fn write_svg(&self, path: &str) -> std::io::Result<()> {
+        File::create(path)?.write_all(self.svg().as_bytes())
+    }
 }
 
-pub fn solve_2(plan: &str) -> i64 {
-    let mut prev = (0, 0);
-    let mut current = (0, 0);
-    let mut perimeter = 0;
-    let mut triangles_sum = 0;
-
-    for instr in plan.lines() {
-        let steps = i64::from_str_radix(&instr[instr.len() - 7..instr.len() - 2], 16).unwrap();
-        let direction = instr.as_bytes()[instr.len() - 2] - b'0';
-        let (d_x, d_y) = DELTAS[direction as usize];
-
-        current = (current.0 + d_x * steps, current.1 + d_y * steps);
-        perimeter += steps;
-        triangles_sum += (prev.1 + current.1) * (prev.0 - current.0);
-        prev = current;
-    }
+impl Instruction {
+    fn new(instruction: &str, encoding: Encoding) -> Instruction {
+        let split = instruction.split(' ').collect_vec();
+        let colour = split[2].trim_start_matches('(').trim_end_matches(')');
 
-    i64::abs(triangles_sum / 2) + perimeter / 2 + 1
+        match encoding {
+            Encoding::DirectionSteps => Instruction {
+                direction: match split[0] {
+                    "U" => Direction::Up,
+                    "R" => Direction::Right,
+                    "D" => Direction::Down,
+                    "L" => Direction::Left,
+                    _ => panic!("Invalid direction: {}", split[0]),
+                },
+                steps: i64::from_str(split[1]).unwrap(),
+                colour: colour.to_string(),
+            },
+            Encoding::HexColour => Instruction {
+                direction: match &colour[6..] {
+                    "0" => Direction::Right,
+                    "1" => Direction::Down,
+                    "2" => Direction::Left,
+                    "3" => Direction::Up,
+                    _ => panic!("Invalid direction: {}", &colour[6..]),
+                },
+                steps: i64::from_str_radix(&colour[1..6], 16).unwrap(),
+                colour: colour.to_string(),
+            },
+        }
+    }
 }
 
 #[cfg(test)]
@@ -75,6 +176,72 @@ mod tests {
         assert_eq!(62, solve_1(sample));
     }
 
+    #[test]
+    fn day_18_dig_plan() {
+        let sample = "R 6 (#70c710)\n\
+            D 5 (#0dc571)\n\
+            L 2 (#5713f0)\n\
+            D 2 (#d2c081)\n\
+            R 2 (#59c680)\n\
+            D 2 (#411b91)\n\
+            L 5 (#8ceee2)\n\
+            U 2 (#caa173)\n\
+            L 1 (#1b58a2)\n\
+            U 2 (#caa171)\n\
+            R 2 (#7807d2)\n\
+            U 3 (#a77fa3)\n\
+            L 2 (#015232)\n\
+            U 2 (#7a21e3)";
+
+        let trench = DigPlan::new(sample, Encoding::DirectionSteps).polygon();
+        assert_eq!(42, trench.area());
+        assert_eq!(38, trench.boundary_points());
+        assert_eq!(24, trench.interior_points());
+        assert!(trench.contains((1, 1)));
+        assert!(!trench.contains((0, 3)));
+
+        let trench = DigPlan::new(sample, Encoding::HexColour).polygon();
+        assert_eq!(&(461_937, 0), &trench.vertices()[1]);
+    }
+
+    #[test]
+    fn day_18_svg() {
+        let sample = "R 6 (#70c710)\n\
+            D 5 (#0dc571)\n\
+            L 2 (#5713f0)\n\
+            D 2 (#d2c081)\n\
+            R 2 (#59c680)\n\
+            D 2 (#411b91)\n\
+            L 5 (#8ceee2)\n\
+            U 2 (#caa173)\n\
+            L 1 (#1b58a2)\n\
+            U 2 (#caa171)\n\
+            R 2 (#7807d2)\n\
+            U 3 (#a77fa3)\n\
+            L 2 (#015232)\n\
+            U 2 (#7a21e3)";
+
+        let svg = DigPlan::new(sample, Encoding::DirectionSteps).svg();
+        assert!(svg.starts_with(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"687\" height=\"1020\" viewBox=\"0 0 686.7 1020.0\">\n  <polygon points=\"10.0,10.0 676.7,10.0"
+        ));
+        assert!(svg.contains(
+            "<line x1=\"10.0\" y1=\"10.0\" x2=\"676.7\" y2=\"10.0\" stroke=\"#70c710\" stroke-width=\"10.0\" stroke-linecap=\"square\"/>"
+        ));
+        assert_eq!(14, svg.matches("<line").count());
+        assert!(svg.ends_with("</svg>\n"));
+
+        // The huge lagoon is scaled down to the same size
+        let svg = DigPlan::new(sample, Encoding::HexColour).svg();
+        assert!(svg.contains("width=\"1020\""));
+        assert!(svg.contains("stroke=\"#7a21e3\" stroke-width=\"1.0\""));
+
+        assert_eq!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"0\" height=\"0\"/>\n",
+            DigPlan::new("", Encoding::DirectionSteps).svg()
+        );
+    }
+
     #[test]
     fn day_18_part_01_solution() {
         let input = include_str!("../../inputs/day_18.txt");
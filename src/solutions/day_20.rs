@@ -1,31 +1,28 @@
 use std::collections::VecDeque;
+use std::fmt::{Display, Formatter};
 use std::ops::Not;
 
 use itertools::Itertools;
-use rustc_hash::FxHashMap;
+use rustc_hash::{FxHashMap, FxHashSet};
 
 use crate::util::lcm;
 
 pub fn solve_1(modules: &[&str]) -> u64 {
-    let mut modules = parse_modules(modules);
-    let mut results = Vec::new();
+    let run = Machine::new(modules).run(1000);
+    let high: u64 = run.counts.values().map(|c| c.received_high).sum();
+    let low: u64 = run.counts.values().map(|c| c.received_low).sum();
 
-    for _ in 0..1000 {
-        let result = push_button(&mut modules, "");
-        results.push(result)
-    }
-
-    results.iter().map(|r| r.high).sum::<u64>() * results.iter().map(|r| r.low).sum::<u64>()
+    high * low
 }
 
-/// Inspecting the input file shows the "rx" module receives pulses through "ql".
-/// "ql" is a conjunction which will emit a low pulse if and only if its 4 inputs: ["fh", "mf", "fz", "ss"],
-/// are all high.
+/// The "rx" module receives pulses through a single conjunction, which in turn listens to one inverter per
+/// binary counter driven by the broadcaster.
 ///
-/// Printing the graph of inputs using [GraphViz](https://graphviz.org) shows 4 independent loops,
-/// ending in the inputs above.
+/// Every counter fires once its count matches the bit pattern wired into its conjunction, after which it resets.
+/// Re-applying the solution of Day 8, and applying LCM on the periods of these counters yields the solution.
 ///
-/// Re-applying the solution of Day 8, and applying LCM on the cycle lengths of these loop yields the solution.
+/// The counters were originally found by printing the graph of inputs using [GraphViz](https://graphviz.org),
+/// which is what [`Machine::counters`] now does by itself.
 ///
 /// The graph source can be found [here](../../graphviz/day_20.dot)
 /// The rendered graph can be found [here](../../graphviz/day_20.png)
@@ -33,120 +30,416 @@ pub fn solve_1(modules: &[&str]) -> u64 {
 /// The broadcast module and the 4 cycle start modules are shown in light blue.
 /// The "rx" module, its source and the 4 modules finishing up the cycles are shown in light green.
 pub fn solve_2(modules: &[&str]) -> u64 {
-    ["fh", "mf", "fz", "ss"]
-        .iter()
-        .map(|&m| find_cycle(&mut parse_modules(modules), m))
-        .fold(1, lcm)
+    Machine::new(modules).rx_period().unwrap()
+}
+
+/// How many pulses of every type a module sent and received
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq)]
+pub struct PulseCounts {
+    pub sent_high: u64,
+    pub sent_low: u64,
+    pub received_high: u64,
+    pub received_low: u64,
+}
+
+/// The outcome of pressing the button a number of times
+#[derive(Debug)]
+pub struct Run<'a> {
+    /// Counts per module, including the button and modules without a definition (like "output")
+    pub counts: FxHashMap<&'a str, PulseCounts>,
+    /// The flip-flop states after every press, in the order of [`Machine::flip_flops`]
+    pub states: Vec<Vec<bool>>,
 }
 
-fn find_cycle(modules: &mut FxHashMap<&str, Module>, cycle_module: &str) -> u64 {
-    let mut cycle_length = 0;
-    let mut result = PulseResult {
-        high: 0,
-        low: 0,
-        cycle: false,
-    };
-
-    while result.cycle.not() {
-        result = push_button(modules, cycle_module);
-        cycle_length += 1;
+/// A chain of flip-flops counting button presses in binary, feeding back through a conjunction
+///
+/// The flip-flops wired into the conjunction form the bit pattern, once the count reaches it the conjunction
+/// sends a low pulse to all other flip-flops and the first one, rolling the counter over to zero.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Counter<'a> {
+    /// The flip-flops from the least to the most significant bit, the first one being driven by the broadcaster
+    pub flip_flops: Vec<&'a str>,
+    pub conjunction: &'a str,
+    /// Which flip-flops are wired into the conjunction, from the least to the most significant bit
+    pub bits: Vec<bool>,
+    /// Destinations of the conjunction outside the counter
+    pub outputs: Vec<&'a str>,
+}
+
+impl Counter<'_> {
+    /// Number of presses between two pulses of the counter's outputs
+    pub fn period(&self) -> u64 {
+        self.bits
+            .iter()
+            .rev()
+            .fold(0, |period, &bit| period * 2 + bit as u64)
     }
 
-    cycle_length
+    /// The bit pattern, with the most significant bit first
+    pub fn pattern(&self) -> String {
+        self.bits
+            .iter()
+            .rev()
+            .map(|&bit| if bit { '1' } else { '0' })
+            .collect()
+    }
+}
+
+impl Display for Counter<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "Counter of {} bits: {}",
+            self.flip_flops.len(),
+            self.flip_flops
+                .iter()
+                .map(|ff| format!("%{ff}"))
+                .join(" -> ")
+        )?;
+        writeln!(
+            f,
+            "  &{} fires every 0b{} = {} presses",
+            self.conjunction,
+            self.pattern(),
+            self.period()
+        )?;
+        write!(f, "  and signals {}", self.outputs.join(", "))
+    }
 }
 
-fn push_button(modules: &mut FxHashMap<&str, Module>, cycle_module: &str) -> PulseResult {
-    let mut result = PulseResult {
-        high: 0,
-        low: 0,
-        cycle: false,
-    };
-    let mut queue = VecDeque::new();
-    queue.push_back(Signal {
-        source: "button",
-        destination: "broadcaster",
-        pulse: Pulse::Low,
-    });
-
-    while let Some(signal) = queue.pop_front() {
-        if signal.destination == "ql"
-            && signal.source == cycle_module
-            && matches!(signal.pulse, Pulse::High)
-        {
-            result.cycle = true;
-            return result;
+#[derive(Debug)]
+pub struct Machine<'a> {
+    modules: FxHashMap<&'a str, Module<'a>>,
+    flip_flops: Vec<&'a str>,
+    presses: u64,
+}
+
+impl<'a> Machine<'a> {
+    pub fn new(modules: &[&'a str]) -> Machine<'a> {
+        let modules = parse_modules(modules);
+        let flip_flops = modules
+            .values()
+            .filter(|m| matches!(m, Module::FlipFlop { .. }))
+            .map(|m| m.label())
+            .sorted()
+            .collect();
+
+        Machine {
+            modules,
+            flip_flops,
+            presses: 0,
+        }
+    }
+
+    /// Labels of all flip-flops, sorted alphabetically
+    pub fn flip_flops(&self) -> &[&'a str] {
+        &self.flip_flops
+    }
+
+    /// Whether every flip-flop is on, in the order of [`Machine::flip_flops`]
+    pub fn state(&self) -> Vec<bool> {
+        self.flip_flops
+            .iter()
+            .map(|f| match self.modules[f] {
+                Module::FlipFlop { state, .. } => matches!(state, State::On),
+                _ => unreachable!(),
+            })
+            .collect()
+    }
+
+    /// How many times the button was pressed so far
+    pub fn presses(&self) -> u64 {
+        self.presses
+    }
+
+    pub fn run(&mut self, presses: u64) -> Run<'a> {
+        let mut counts: FxHashMap<&str, PulseCounts> = FxHashMap::default();
+        let mut states = Vec::new();
+
+        for _ in 0..presses {
+            self.press(|signal| {
+                let sender = counts.entry(signal.source).or_default();
+                match signal.pulse {
+                    Pulse::High => sender.sent_high += 1,
+                    Pulse::Low => sender.sent_low += 1,
+                }
+
+                let receiver = counts.entry(signal.destination).or_default();
+                match signal.pulse {
+                    Pulse::High => receiver.received_high += 1,
+                    Pulse::Low => receiver.received_low += 1,
+                }
+            });
+            states.push(self.state());
         }
 
-        match signal.pulse {
-            Pulse::High => result.high += 1,
-            Pulse::Low => result.low += 1,
+        Run { counts, states }
+    }
+
+    /// Keeps pressing the button until `target` receives `pulse` (optionally only counting pulses from `source`)
+    ///
+    /// Returns the number of the press on which that happened, counting all presses of this machine,
+    /// or None when it did not happen within `max_presses` more presses.
+    pub fn first_press(
+        &mut self,
+        target: &str,
+        pulse: Pulse,
+        source: Option<&str>,
+        max_presses: u64,
+    ) -> Option<u64> {
+        for _ in 0..max_presses {
+            let mut received = false;
+            self.press(|signal| {
+                received |= signal.destination == target
+                    && signal.pulse == pulse
+                    && source.is_none_or(|s| s == signal.source)
+            });
+
+            if received {
+                return Some(self.presses);
+            }
         }
 
-        let module = match modules.get_mut(signal.destination) {
-            None => continue,
-            Some(m) => m,
+        None
+    }
+
+    /// Recognises the binary counters driven by the broadcaster, from the wiring alone
+    pub fn counters(&self) -> Vec<Counter<'a>> {
+        self.modules
+            .get("broadcaster")
+            .map(|b| {
+                b.destinations()
+                    .iter()
+                    .flat_map(|&d| self.counter(d))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Number of presses until "rx" receives a low pulse, derived from the counters driving it
+    ///
+    /// Fails unless "rx" is fed by a single conjunction, of which every input is driven by a counter.
+    pub fn rx_period(&self) -> Result<u64, String> {
+        let counters = self.counters();
+        if counters.is_empty() {
+            return Err("No counters found in the circuit".to_string());
+        }
+
+        let feeding = self.inputs("rx");
+        let &[conjunction] = &feeding[..] else {
+            return Err(format!(
+                "Expected a single conjunction feeding rx, found {:?}",
+                feeding
+            ));
+        };
+        let Module::Conjunction { state: inputs, .. } = &self.modules[conjunction] else {
+            return Err(format!(
+                "Expected rx to be fed by a conjunction, found {}",
+                self.modules[conjunction].describe()
+            ));
         };
-        let source = module.label();
 
-        match module {
-            Module::FlipFlop {
-                label: _,
-                ref mut state,
-                destinations,
-            } => match signal.pulse {
-                Pulse::High => {}
-                Pulse::Low => {
+        // Only the counters wired (through their outputs) into the conjunction decide when rx receives a low pulse
+        let counters = counters
+            .into_iter()
+            .filter(|c| c.outputs.iter().any(|o| inputs.contains_key(o)))
+            .collect_vec();
+        let outputs: FxHashSet<_> = counters.iter().flat_map(|c| c.outputs.iter()).collect();
+        let undriven = inputs
+            .keys()
+            .filter(|i| outputs.contains(i).not())
+            .sorted()
+            .collect_vec();
+        if undriven.is_empty().not() {
+            return Err(format!(
+                "Inputs {:?} of &{} are not driven by any counter",
+                undriven, conjunction
+            ));
+        }
+
+        Ok(counters.iter().map(|c| c.period()).fold(1, lcm))
+    }
+
+    /// Describes the counters and lists all modules not part of any of them
+    pub fn summary(&self) -> String {
+        let counters = self.counters();
+        let in_counter: FxHashSet<_> = counters
+            .iter()
+            .flat_map(|c| c.flip_flops.iter().chain(std::iter::once(&c.conjunction)))
+            .collect();
+
+        let mut summary = format!("broadcaster drives {} counters\n", counters.len());
+        counters
+            .iter()
+            .for_each(|c| summary.push_str(&format!("{c}\n")));
+        summary.push_str(&format!(
+            "All counters fire on the same press every {} presses\n",
+            counters.iter().map(|c| c.period()).fold(1, lcm)
+        ));
+
+        self.modules
+            .values()
+            .filter(|m| m.label() != "broadcaster" && in_counter.contains(&m.label()).not())
+            .sorted_by_key(|m| m.label())
+            .for_each(|m| summary.push_str(&format!("{}\n", m.describe())));
+
+        summary
+    }
+
+    /// Labels of all modules sending pulses to the given module, sorted alphabetically
+    fn inputs(&self, label: &str) -> Vec<&'a str> {
+        self.modules
+            .values()
+            .filter(|m| m.destinations().contains(&label))
+            .map(|m| m.label())
+            .sorted()
+            .collect()
+    }
+
+    fn counter(&self, first: &'a str) -> Option<Counter<'a>> {
+        let is_flip_flop =
+            |label: &str| matches!(self.modules.get(label), Some(Module::FlipFlop { .. }));
+        let is_conjunction =
+            |label: &str| matches!(self.modules.get(label), Some(Module::Conjunction { .. }));
+
+        if is_flip_flop(first).not() {
+            return None;
+        }
+
+        // Each flip-flop drives at most the next one, where the last one drives none
+        let mut flip_flops = vec![first];
+        loop {
+            let next = self.modules[flip_flops.last().unwrap()]
+                .destinations()
+                .iter()
+                .filter(|&&d| is_flip_flop(d))
+                .collect_vec();
+
+            match next[..] {
+                [] => break,
+                [&next] if flip_flops.contains(&next).not() => flip_flops.push(next),
+                _ => return None,
+            }
+        }
+
+        let conjunctions = flip_flops
+            .iter()
+            .flat_map(|f| self.modules[f].destinations())
+            .filter(|&&d| is_conjunction(d))
+            .unique()
+            .collect_vec();
+        let &[&conjunction] = &conjunctions[..] else {
+            return None;
+        };
+
+        let bits = flip_flops
+            .iter()
+            .map(|f| self.modules[f].destinations().contains(&conjunction))
+            .collect_vec();
+        let resets = self.modules[conjunction].destinations();
+        let Module::Conjunction { state: inputs, .. } = &self.modules[conjunction] else {
+            unreachable!()
+        };
+
+        let counts_presses = resets.contains(&first)
+            && flip_flops
+                .iter()
+                .zip(&bits)
+                .all(|(f, &bit)| bit || resets.contains(f))
+            && inputs.len() == bits.iter().filter(|&&bit| bit).count();
+        if counts_presses.not() {
+            return None;
+        }
+
+        Some(Counter {
+            outputs: resets
+                .iter()
+                .filter(|d| flip_flops.contains(d).not())
+                .copied()
+                .collect(),
+            flip_flops,
+            conjunction,
+            bits,
+        })
+    }
+
+    fn press(&mut self, mut on_signal: impl FnMut(&Signal<'a>)) {
+        self.presses += 1;
+
+        let mut queue = VecDeque::new();
+        queue.push_back(Signal {
+            source: "button",
+            destination: "broadcaster",
+            pulse: Pulse::Low,
+        });
+
+        while let Some(signal) = queue.pop_front() {
+            on_signal(&signal);
+
+            let module = match self.modules.get_mut(signal.destination) {
+                None => continue,
+                Some(m) => m,
+            };
+            let source = module.label();
+
+            match module {
+                Module::FlipFlop {
+                    label: _,
+                    ref mut state,
+                    destinations,
+                } => match signal.pulse {
+                    Pulse::High => {}
+                    Pulse::Low => {
+                        destinations.iter().for_each(|&d| {
+                            queue.push_back(Signal {
+                                source,
+                                destination: d,
+                                pulse: match state {
+                                    State::On => Pulse::Low,
+                                    State::Off => Pulse::High,
+                                },
+                            })
+                        });
+                        match state {
+                            State::On => *state = State::Off,
+                            State::Off => *state = State::On,
+                        };
+                    }
+                },
+                Module::Conjunction {
+                    label: _,
+                    ref mut state,
+                    destinations,
+                } => {
+                    state.insert(signal.source, signal.pulse);
+
+                    let pulse = if state.values().all(|p| matches!(p, Pulse::High)) {
+                        Pulse::Low
+                    } else {
+                        Pulse::High
+                    };
+
                     destinations.iter().for_each(|&d| {
                         queue.push_back(Signal {
                             source,
                             destination: d,
-                            pulse: match state {
-                                State::On => Pulse::Low,
-                                State::Off => Pulse::High,
-                            },
+                            pulse,
                         })
                     });
-                    match state {
-                        State::On => *state = State::Off,
-                        State::Off => *state = State::On,
-                    };
                 }
-            },
-            Module::Conjunction {
-                label: _,
-                ref mut state,
-                destinations,
-            } => {
-                state.insert(signal.source, signal.pulse);
-
-                let pulse = if state.values().all(|p| matches!(p, Pulse::High)) {
-                    Pulse::Low
-                } else {
-                    Pulse::High
-                };
-
-                destinations.iter().for_each(|&d| {
+                Module::Broadcast {
+                    label: _,
+                    destinations,
+                } => destinations.iter().for_each(|&d| {
                     queue.push_back(Signal {
                         source,
                         destination: d,
-                        pulse,
-                    })
-                });
+                        pulse: signal.pulse,
+                    });
+                }),
             }
-            Module::Broadcast {
-                label: _,
-                destinations,
-            } => destinations.iter().for_each(|&d| {
-                queue.push_back(Signal {
-                    source,
-                    destination: d,
-                    pulse: signal.pulse,
-                });
-            }),
         }
     }
-
-    result
 }
 
 fn parse_modules<'a>(modules: &[&'a str]) -> FxHashMap<&'a str, Module<'a>> {
@@ -176,7 +469,7 @@ fn parse_modules<'a>(modules: &[&'a str]) -> FxHashMap<&'a str, Module<'a>> {
     for conjunction in conjunctions {
         let incoming_modules = modules
             .values()
-            .filter(|&m| m.destinations().iter().any(|&d| d == conjunction))
+            .filter(|&m| m.destinations().contains(&conjunction))
             .map(|m| m.label())
             .collect_vec();
         let state = modules
@@ -242,6 +535,22 @@ impl<'a> Module<'a> {
         }
     }
 
+    /// The module written as in the input, e.g. "%a -> inv, con"
+    fn describe(&self) -> String {
+        let prefix = match self {
+            Module::FlipFlop { .. } => "%",
+            Module::Conjunction { .. } => "&",
+            Module::Broadcast { .. } => "",
+        };
+
+        format!(
+            "{}{} -> {}",
+            prefix,
+            self.label(),
+            self.destinations().join(", ")
+        )
+    }
+
     fn destinations(&self) -> &Vec<&'a str> {
         match self {
             Module::FlipFlop { destinations, .. } => destinations,
@@ -264,19 +573,12 @@ enum State {
     Off,
 }
 
-#[derive(Debug, Copy, Clone)]
-enum Pulse {
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Pulse {
     High,
     Low,
 }
 
-#[derive(Debug)]
-struct PulseResult {
-    high: u64,
-    low: u64,
-    cycle: bool,
-}
-
 #[derive(Debug)]
 struct Signal<'a> {
     source: &'a str,
@@ -333,4 +635,160 @@ mod tests {
 
         assert_eq!(212_986_464_842_911, solve_2(&input));
     }
+
+    #[test]
+    fn day_20_run() {
+        let sample = vec![
+            "broadcaster -> a",
+            "%a -> inv, con",
+            "&inv -> b",
+            "%b -> con",
+            "&con -> output",
+        ];
+        let mut machine = Machine::new(&sample);
+        assert_eq!(vec!["a", "b"], machine.flip_flops());
+
+        let run = machine.run(4);
+        assert_eq!(
+            vec![
+                vec![true, true],
+                vec![false, true],
+                vec![true, false],
+                vec![false, false]
+            ],
+            run.states
+        );
+        assert_eq!(
+            PulseCounts {
+                sent_high: 0,
+                sent_low: 4,
+                received_high: 0,
+                received_low: 0
+            },
+            run.counts["button"]
+        );
+        assert_eq!(
+            PulseCounts {
+                sent_high: 4,
+                sent_low: 4,
+                received_high: 0,
+                received_low: 4
+            },
+            run.counts["a"]
+        );
+        assert_eq!(
+            PulseCounts {
+                sent_high: 0,
+                sent_low: 0,
+                received_high: 4,
+                received_low: 2
+            },
+            run.counts["output"]
+        );
+        assert_eq!(4, machine.presses());
+
+        // The output briefly receives a low pulse on every press turning "a" on
+        let mut machine = Machine::new(&sample);
+        assert_eq!(Some(1), machine.first_press("output", Pulse::Low, None, 10));
+        assert_eq!(Some(3), machine.first_press("output", Pulse::Low, None, 10));
+        assert_eq!(
+            Some(5),
+            machine.first_press("con", Pulse::High, Some("b"), 10)
+        );
+        assert_eq!(None, machine.first_press("inv", Pulse::High, Some("b"), 10));
+    }
+
+    #[test]
+    fn day_20_counters() {
+        let sample = vec![
+            "broadcaster -> a0, b0",
+            "%a0 -> a1, ca",
+            "%a1 -> a2, ca",
+            "%a2 -> a3",
+            "%a3 -> ca",
+            "&ca -> a0, a2, ia",
+            "&ia -> all",
+            "%b0 -> b1, cb",
+            "%b1 -> b2",
+            "%b2 -> cb",
+            "&cb -> b0, b1, ib",
+            "&ib -> all",
+            "&all -> rx",
+        ];
+        let machine = Machine::new(&sample);
+        let counters = machine.counters();
+
+        assert_eq!(2, counters.len());
+        assert_eq!(vec!["a0", "a1", "a2", "a3"], counters[0].flip_flops);
+        assert_eq!("ca", counters[0].conjunction);
+        assert_eq!("1011", counters[0].pattern());
+        assert_eq!(11, counters[0].period());
+        assert_eq!(vec!["ib"], counters[1].outputs);
+        assert_eq!(5, counters[1].period());
+        assert_eq!(
+            "broadcaster drives 2 counters\n\
+             Counter of 4 bits: %a0 -> %a1 -> %a2 -> %a3\n  \
+               &ca fires every 0b1011 = 11 presses\n  \
+               and signals ia\n\
+             Counter of 3 bits: %b0 -> %b1 -> %b2\n  \
+               &cb fires every 0b101 = 5 presses\n  \
+               and signals ib\n\
+             All counters fire on the same press every 55 presses\n\
+             &all -> rx\n\
+             &ia -> all\n\
+             &ib -> all\n",
+            machine.summary()
+        );
+
+        // The wiring agrees with actually pressing the button
+        assert_eq!(55, solve_2(&sample));
+        assert_eq!(
+            Some(55),
+            Machine::new(&sample).first_press("rx", Pulse::Low, None, 100)
+        );
+
+        // Without the conjunction resetting the flip-flops there is no counter
+        let broken = vec!["broadcaster -> a", "%a -> b, c", "%b -> c", "&c -> rx"];
+        assert!(Machine::new(&broken).counters().is_empty());
+        assert_eq!(
+            Err("No counters found in the circuit".to_string()),
+            Machine::new(&broken).rx_period()
+        );
+
+        // The final conjunction also listens to a module outside of any counter
+        let mut partial = sample.clone();
+        partial.push("%extra -> all");
+        assert_eq!(
+            Err("Inputs [\"extra\"] of &all are not driven by any counter".to_string()),
+            Machine::new(&partial).rx_period()
+        );
+
+        let mut two_feeders = sample.clone();
+        two_feeders.push("&other -> rx");
+        assert_eq!(
+            Err("Expected a single conjunction feeding rx, found [\"all\", \"other\"]".to_string()),
+            Machine::new(&two_feeders).rx_period()
+        );
+
+        let mut flip_flop_feeder = sample.clone();
+        *flip_flop_feeder.last_mut().unwrap() = "%all -> rx";
+        assert_eq!(
+            Err("Expected rx to be fed by a conjunction, found %all -> rx".to_string()),
+            Machine::new(&flip_flop_feeder).rx_period()
+        );
+
+        // A counter signalling something else than rx does not change when rx is reached
+        let mut unrelated = sample.clone();
+        unrelated[0] = "broadcaster -> a0, b0, c0";
+        unrelated.extend([
+            "%c0 -> c1, cc",
+            "%c1 -> cc",
+            "&cc -> c0, ic",
+            "&ic -> output",
+        ]);
+        let counters = Machine::new(&unrelated).counters();
+        assert_eq!(3, counters.len());
+        assert_eq!(3, counters[2].period());
+        assert_eq!(Ok(55), Machine::new(&unrelated).rx_period());
+    }
 }
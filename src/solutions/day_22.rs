@@ -1,11 +1,11 @@
 use std::cmp::Ordering;
-use std::collections::VecDeque;
+use std::fmt::{Display, Formatter};
 use std::str::FromStr;
 
 use itertools::Itertools;
 use rustc_hash::{FxHashMap, FxHashSet};
 
-type Label = usize;
+pub type Label = usize;
 
 const GROUND_L: Label = usize::MAX;
 const GROUND_H: LabeledHeight = LabeledHeight {
@@ -18,51 +18,41 @@ pub fn solve_1(snapshot: &[&str]) -> u32 {
 }
 
 pub fn solve_2(snapshot: &[&str]) -> u32 {
-    let stack = Stack::new(snapshot);
-
-    stack
-        .bricks
-        .iter()
-        .map(|b| b.label)
-        .map(|b| stack.clone().chain_reaction(b))
-        .sum()
+    chain_reactions(snapshot).iter().sum()
 }
 
+/// For every brick, the number of other bricks that would fall if it were disintegrated.
+///
+/// The report is indexed by the brick's line in the snapshot.
+pub fn chain_reactions(snapshot: &[&str]) -> Vec<u32> {
+    let reactions = Stack::new(snapshot).chain_reactions();
+
+    (0..snapshot.len()).map(|l| reactions[&l]).collect()
+}
+
+/// A stack of bricks, which can be further demolished or built upon after the initial settling.
+///
+/// Every brick is labeled by its line in the snapshot, bricks added later on get the next free label.
 #[derive(Debug, Clone)]
-struct Stack {
+pub struct Stack {
     bricks: Vec<Brick>,
+    next_label: Label,
     planar_points: FxHashMap<PlaneCoord, LabeledHeight>,
     leans_upon: FxHashMap<Label, FxHashSet<Label>>,
     leaned_upon_by: FxHashMap<Label, FxHashSet<Label>>,
 }
 
 impl Stack {
-    fn new(snapshot: &[&str]) -> Self {
-        let mut bricks = snapshot
+    pub fn new(snapshot: &[&str]) -> Self {
+        let bricks = snapshot
             .iter()
             .enumerate()
-            .map(|(l, &s)| {
-                let coords = s
-                    .split('~')
-                    .map(|p| {
-                        p.split(',')
-                            .map(|n| u16::from_str(n).unwrap())
-                            .collect_vec()
-                    })
-                    .collect_vec();
-
-                Brick {
-                    label: l,
-                    x: (coords[0][0], coords[1][0]),
-                    y: (coords[0][1], coords[1][1]),
-                    z: (coords[0][2], coords[1][2]),
-                }
-            })
+            .map(|(l, &s)| Brick::new(l, s).unwrap_or_else(|| panic!("Invalid brick: {s}")))
             .collect_vec();
-        bricks.sort();
 
         let mut stack = Self {
             bricks,
+            next_label: snapshot.len(),
             planar_points: Default::default(),
             leans_upon: Default::default(),
             leaned_upon_by: Default::default(),
@@ -72,7 +62,73 @@ impl Stack {
         stack
     }
 
-    fn settle(&mut self) {
+    /// Disintegrates a brick, letting the stack settle again.
+    ///
+    /// Returns the labels of all bricks that dropped down as a result, or `None` if the brick is not in the stack.
+    pub fn remove(&mut self, label: Label) -> Option<Vec<Label>> {
+        let idx = self.bricks.iter().position(|b| b.label == label)?;
+        self.bricks.remove(idx);
+
+        Some(self.settle())
+    }
+
+    /// Adds a brick at the given position (in snapshot notation, e.g. `1,0,12~1,2,12`), after which it drops down.
+    ///
+    /// Returns the label of the new brick, or `None` if the position is malformed or would overlap a brick already in the stack.
+    pub fn add(&mut self, brick: &str) -> Option<Label> {
+        let brick = Brick::new(self.next_label, brick)?;
+        if self.bricks.iter().any(|b| b.overlaps(&brick)) {
+            return None;
+        }
+
+        self.bricks.push(brick);
+        self.next_label += 1;
+        self.settle();
+
+        Some(brick.label)
+    }
+
+    /// The current position of a brick, if it is in the stack.
+    pub fn brick(&self, label: Label) -> Option<Brick> {
+        self.bricks.iter().find(|b| b.label == label).copied()
+    }
+
+    /// The labels of all bricks in the stack, from the bottom up.
+    pub fn labels(&self) -> Vec<Label> {
+        self.bricks.iter().map(|b| b.label).collect()
+    }
+
+    /// The bricks the given brick is resting on, empty if it rests on the ground (or is not in the stack).
+    pub fn supported_by(&self, label: Label) -> Vec<Label> {
+        Self::sorted_bricks(self.leans_upon.get(&label))
+    }
+
+    /// The bricks resting on top of the given brick.
+    pub fn supports(&self, label: Label) -> Vec<Label> {
+        Self::sorted_bricks(self.leaned_upon_by.get(&label))
+    }
+
+    fn sorted_bricks(labels: Option<&FxHashSet<Label>>) -> Vec<Label> {
+        labels
+            .into_iter()
+            .flatten()
+            .filter(|&&l| l != GROUND_L)
+            .copied()
+            .sorted()
+            .collect()
+    }
+
+    /// Lets all bricks drop down as far as possible, rebuilding the support relations from scratch.
+    ///
+    /// Returns the labels of all bricks that dropped down.
+    fn settle(&mut self) -> Vec<Label> {
+        self.bricks.sort();
+        self.planar_points.clear();
+        self.leans_upon.clear();
+        self.leaned_upon_by.clear();
+
+        let mut dropped = Vec::new();
+
         for brick in &mut self.bricks {
             // For every x,y, find the drop distance of Z
             let z_drop = Self::x_y_iter(brick)
@@ -81,6 +137,9 @@ impl Stack {
                 .unwrap();
             brick.z.0 -= z_drop;
             brick.z.1 -= z_drop;
+            if z_drop > 0 {
+                dropped.push(brick.label);
+            }
 
             // For every x,y, find whether we lean on another brick (has z value new_z-1),
             // and put them in a Vec after running "unique" for de-duplication, if we lean across multiple cubes
@@ -110,6 +169,8 @@ impl Stack {
                 self.planar_points.insert(p, lh);
             })
         }
+
+        dropped
     }
 
     fn count_disintegratable(&self) -> u32 {
@@ -126,31 +187,81 @@ impl Stack {
             .count() as u32
     }
 
-    fn chain_reaction(mut self, init_brick: Label) -> u32 {
-        let mut falling = 0;
-        let mut to_remove: VecDeque<Label> = VecDeque::new();
-        to_remove.push_back(init_brick);
-
-        while let Some(brick) = to_remove.pop_front() {
-            // When removing a brick, check all bricks that were leaning on it
-            if let Some(leaned_upon) = self.leaned_upon_by.get_mut(&brick) {
-                for leaning in leaned_upon.iter() {
-                    // For every brick leaning on it, discard the removed brick
-                    let leaning_on = self.leans_upon.get_mut(leaning).unwrap();
-                    leaning_on.remove(&brick);
-
-                    // If we now find that the newly inspected brick is leaning on nothing else,
-                    // it starts falling, and it too should be removed,
-                    // repeating the procedure until no more bricks are falling
-                    if leaning_on.is_empty() {
-                        to_remove.push_front(*leaning);
-                        falling += 1;
-                    }
-                }
+    /// A brick falls when brick B is removed if and only if every path from the ground up to it passes through B,
+    /// in other words, if B dominates it in the support graph (with the ground as root).
+    ///
+    /// The bricks are settled bottom-up, so `self.bricks` is a topological order of this graph.
+    /// This allows building the dominator tree in a single pass:
+    /// the immediate dominator of a brick is the lowest common ancestor (in the dominator tree) of all bricks it leans upon.
+    /// The chain reaction of a brick is then the size of its subtree in the dominator tree, minus the brick itself.
+    pub fn chain_reactions(&self) -> FxHashMap<Label, u32> {
+        // Node 0 is the ground, node i + 1 is the i-th brick in settling order
+        let nr_nodes = self.bricks.len() + 1;
+        let node_of: FxHashMap<Label, usize> = self
+            .bricks
+            .iter()
+            .enumerate()
+            .map(|(idx, b)| (b.label, idx + 1))
+            .chain([(GROUND_L, 0)])
+            .collect();
+
+        // Binary lifting table, ancestors[k][n] is the 2^k-th dominator of node n
+        let levels = (usize::BITS - nr_nodes.leading_zeros()) as usize;
+        let mut ancestors = vec![vec![0; nr_nodes]; levels.max(1)];
+        let mut depth = vec![0; nr_nodes];
+
+        for (idx, brick) in self.bricks.iter().enumerate() {
+            let node = idx + 1;
+            let idom = self.leans_upon[&brick.label]
+                .iter()
+                .map(|l| node_of[l])
+                .reduce(|a, b| Self::lca(&ancestors, &depth, a, b))
+                .unwrap();
+
+            depth[node] = depth[idom] + 1;
+            ancestors[0][node] = idom;
+            for k in 1..ancestors.len() {
+                ancestors[k][node] = ancestors[k - 1][ancestors[k - 1][node]];
+            }
+        }
+
+        // Walking the bricks top-down, every brick adds its subtree to its immediate dominator
+        let mut subtree_sizes = vec![1; nr_nodes];
+        for node in (1..nr_nodes).rev() {
+            subtree_sizes[ancestors[0][node]] += subtree_sizes[node];
+        }
+
+        self.bricks
+            .iter()
+            .enumerate()
+            .map(|(idx, b)| (b.label, subtree_sizes[idx + 1] - 1))
+            .collect()
+    }
+
+    fn lca(ancestors: &[Vec<usize>], depth: &[usize], mut a: usize, mut b: usize) -> usize {
+        if depth[a] < depth[b] {
+            (a, b) = (b, a);
+        }
+
+        // Lift the deepest node up until both are at the same depth
+        for k in (0..ancestors.len()).rev() {
+            if depth[a] - depth[b] >= 1 << k {
+                a = ancestors[k][a];
+            }
+        }
+        if a == b {
+            return a;
+        }
+
+        // Lift both nodes up to just below their common ancestor
+        for k in (0..ancestors.len()).rev() {
+            if ancestors[k][a] != ancestors[k][b] {
+                a = ancestors[k][a];
+                b = ancestors[k][b];
             }
         }
 
-        falling
+        ancestors[0][a]
     }
 
     fn x_y_iter(brick: &Brick) -> impl Iterator<Item = PlaneCoord> + '_ {
@@ -160,13 +271,57 @@ impl Stack {
 }
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
-struct Brick {
+pub struct Brick {
     label: Label,
     x: (u16, u16),
     y: (u16, u16),
     z: (u16, u16),
 }
 
+impl Brick {
+    fn new(label: Label, brick: &str) -> Option<Self> {
+        let parse = |position: &str| -> Option<[u16; 3]> {
+            let coords: Vec<_> = position
+                .split(',')
+                .map(u16::from_str)
+                .collect::<Result<_, _>>()
+                .ok()?;
+            coords.try_into().ok()
+        };
+
+        let (start, end) = brick.split_once('~')?;
+        let ([x_s, y_s, z_s], [x_e, y_e, z_e]) = (parse(start)?, parse(end)?);
+
+        // Ranges run from low to high, and the ground at z=0 is not part of the air
+        if x_s > x_e || y_s > y_e || z_s > z_e || z_s < 1 {
+            return None;
+        }
+
+        Some(Brick {
+            label,
+            x: (x_s, x_e),
+            y: (y_s, y_e),
+            z: (z_s, z_e),
+        })
+    }
+
+    fn overlaps(&self, other: &Brick) -> bool {
+        let overlap = |a: (u16, u16), b: (u16, u16)| a.0 <= b.1 && b.0 <= a.1;
+
+        overlap(self.x, other.x) && overlap(self.y, other.y) && overlap(self.z, other.z)
+    }
+}
+
+impl Display for Brick {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{},{},{}~{},{},{}",
+            self.x.0, self.y.0, self.z.0, self.x.1, self.y.1, self.z.1
+        )
+    }
+}
+
 impl Ord for Brick {
     fn cmp(&self, other: &Self) -> Ordering {
         self.z.0.cmp(&other.z.0).then(self.label.cmp(&other.label))
@@ -234,6 +389,50 @@ mod tests {
         ];
 
         assert_eq!(7, solve_2(&sample));
+        assert_eq!(vec![6, 0, 0, 0, 0, 1, 0], chain_reactions(&sample));
+    }
+
+    #[test]
+    fn day_22_demolition() {
+        let sample = vec![
+            "1,0,1~1,2,1",
+            "0,0,2~2,0,2",
+            "0,2,3~2,2,3",
+            "0,0,4~0,2,4",
+            "2,0,5~2,2,5",
+            "0,1,6~2,1,6",
+            "1,1,8~1,1,9",
+        ];
+        let stack = Stack::new(&sample);
+
+        assert_eq!(vec![1, 2], stack.supports(0));
+        assert_eq!(vec![3, 4], stack.supported_by(5));
+        assert_eq!("1,1,5~1,1,6", stack.brick(6).unwrap().to_string());
+
+        // Physically dropping the bricks agrees with the chain reaction counts
+        let reactions = stack.chain_reactions();
+        for label in stack.labels() {
+            let dropped = stack.clone().remove(label).unwrap();
+            assert_eq!(reactions[&label], dropped.len() as u32);
+        }
+
+        let mut stack = stack;
+        assert_eq!(Some(vec![1, 2, 3, 4, 5, 6]), stack.remove(0));
+        assert_eq!(None, stack.remove(0));
+        assert_eq!("1,1,4~1,1,5", stack.brick(6).unwrap().to_string());
+
+        assert_eq!(None, stack.add("0,0,1~0,2,1"));
+        assert_eq!(None, stack.add("1,0,10~1,2"));
+        assert_eq!(None, stack.add("1,0,10"));
+        assert_eq!(None, stack.add("1,0,x~1,2,10"));
+        assert_eq!(None, stack.add("1,0,0~1,2,0"));
+        assert_eq!(None, stack.add("1,2,10~1,0,10"));
+        assert_eq!(None, stack.add("2,0,10~1,0,10"));
+        assert_eq!(None, stack.add("1,0,11~1,0,10"));
+        assert_eq!(Some(7), stack.add("1,0,10~1,2,10"));
+        assert_eq!("1,0,6~1,2,6", stack.brick(7).unwrap().to_string());
+        assert_eq!(vec![6], stack.supported_by(7));
+        assert_eq!(vec![7], stack.supports(6));
     }
 
     #[test]
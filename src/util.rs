@@ -1,3 +1,6 @@
+pub mod geometry;
+pub mod interval;
+
 pub fn lcm(a: u64, b: u64) -> u64 {
     a / gcd(a, b) * b
 }
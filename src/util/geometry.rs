@@ -0,0 +1,166 @@
+use crate::util::gcd;
+
+pub type Point = (i64, i64);
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Direction {
+    Up,
+    Right,
+    Down,
+    Left,
+}
+
+impl Direction {
+    /// The unit step, with y growing downwards.
+    pub fn delta(&self) -> Point {
+        match self {
+            Direction::Up => (0, -1),
+            Direction::Right => (1, 0),
+            Direction::Down => (0, 1),
+            Direction::Left => (-1, 0),
+        }
+    }
+}
+
+/// A closed polygon on the integer grid, with an edge from every vertex to the next (and from the last to the first).
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Polygon {
+    vertices: Vec<Point>,
+}
+
+impl Polygon {
+    pub fn new(vertices: Vec<Point>) -> Polygon {
+        Polygon { vertices }
+    }
+
+    /// Builds the polygon by starting at the origin and moving the given number of steps in every direction.
+    ///
+    /// The moves are expected to end back at the origin.
+    pub fn from_moves(moves: impl IntoIterator<Item = (Direction, i64)>) -> Polygon {
+        let mut current = (0, 0);
+        let mut vertices = vec![current];
+
+        for (direction, steps) in moves {
+            let (d_x, d_y) = direction.delta();
+            current = (current.0 + d_x * steps, current.1 + d_y * steps);
+            vertices.push(current);
+        }
+
+        if vertices.last() == Some(&(0, 0)) {
+            vertices.pop();
+        }
+
+        Polygon { vertices }
+    }
+
+    pub fn vertices(&self) -> &[Point] {
+        &self.vertices
+    }
+
+    fn edges(&self) -> impl Iterator<Item = (Point, Point)> + '_ {
+        self.vertices
+            .iter()
+            .copied()
+            .zip(self.vertices.iter().copied().cycle().skip(1))
+    }
+
+    /// Twice the enclosed area, using the shoelace formula (always a whole number on the integer grid).
+    pub fn double_area(&self) -> i64 {
+        self.edges()
+            .map(|(a, b)| a.0 * b.1 - b.0 * a.1)
+            .sum::<i64>()
+            .abs()
+    }
+
+    /// The enclosed area, exact for rectilinear polygons.
+    pub fn area(&self) -> i64 {
+        self.double_area() / 2
+    }
+
+    /// The number of grid points lying on the edges.
+    pub fn boundary_points(&self) -> i64 {
+        self.edges()
+            .map(|(a, b)| gcd((a.0 - b.0).unsigned_abs(), (a.1 - b.1).unsigned_abs()) as i64)
+            .sum()
+    }
+
+    /// The number of grid points strictly inside, using Pick's theorem: A = i + b/2 - 1.
+    pub fn interior_points(&self) -> i64 {
+        (self.double_area() - self.boundary_points()) / 2 + 1
+    }
+
+    /// The number of grid points either inside or on the edges.
+    pub fn lattice_points(&self) -> i64 {
+        self.interior_points() + self.boundary_points()
+    }
+
+    /// Whether the point lies inside or on the edges of the polygon.
+    pub fn contains(&self, point: Point) -> bool {
+        if self.edges().any(|(a, b)| Self::on_edge(a, b, point)) {
+            return true;
+        }
+
+        // Cast a ray to the right, counting the edges it crosses
+        self.edges()
+            .filter(|&(a, b)| (a.1 > point.1) != (b.1 > point.1))
+            .filter(|&(a, b)| {
+                // The x coordinate of the crossing, compared without dividing
+                let lhs = (point.0 - a.0) * (b.1 - a.1);
+                let rhs = (b.0 - a.0) * (point.1 - a.1);
+                if b.1 > a.1 {
+                    lhs < rhs
+                } else {
+                    lhs > rhs
+                }
+            })
+            .count()
+            % 2
+            == 1
+    }
+
+    fn on_edge(a: Point, b: Point, p: Point) -> bool {
+        let cross = (b.0 - a.0) * (p.1 - a.1) - (b.1 - a.1) * (p.0 - a.0);
+
+        cross == 0
+            && a.0.min(b.0) <= p.0
+            && p.0 <= a.0.max(b.0)
+            && a.1.min(b.1) <= p.1
+            && p.1 <= a.1.max(b.1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn polygon() {
+        // An L-shape: a 4x4 square with a 2x2 square cut out of its top right corner
+        let polygon = Polygon::from_moves([
+            (Direction::Right, 2),
+            (Direction::Down, 2),
+            (Direction::Right, 2),
+            (Direction::Down, 2),
+            (Direction::Left, 4),
+            (Direction::Up, 4),
+        ]);
+
+        assert_eq!(6, polygon.vertices().len());
+        assert_eq!(12, polygon.area());
+        assert_eq!(16, polygon.boundary_points());
+        assert_eq!(5, polygon.interior_points());
+        assert_eq!(21, polygon.lattice_points());
+
+        assert!(polygon.contains((1, 1)));
+        assert!(polygon.contains((2, 1)));
+        assert!(polygon.contains((4, 3)));
+        assert!(!polygon.contains((3, 1)));
+        assert!(!polygon.contains((5, 3)));
+        assert!(!polygon.contains((-1, 0)));
+
+        let triangle = Polygon::new(vec![(0, 0), (4, 0), (0, 4)]);
+        assert_eq!(16, triangle.double_area());
+        assert_eq!(12, triangle.boundary_points());
+        assert_eq!(3, triangle.interior_points());
+    }
+}
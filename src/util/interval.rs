@@ -0,0 +1,351 @@
+use std::cmp::{max, min};
+use std::fmt::{Display, Formatter};
+use std::ops::Range;
+
+use itertools::Itertools;
+
+/// A set of values, stored as sorted ranges which neither overlap nor touch each other.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct IntervalSet {
+    ranges: Vec<Range<u64>>,
+}
+
+impl IntervalSet {
+    pub fn new(ranges: impl IntoIterator<Item = Range<u64>>) -> Self {
+        let mut normalised: Vec<Range<u64>> = Vec::new();
+
+        for range in ranges
+            .into_iter()
+            .filter(|r| !r.is_empty())
+            .sorted_by_key(|r| r.start)
+        {
+            match normalised.last_mut() {
+                Some(last) if range.start <= last.end => last.end = max(last.end, range.end),
+                _ => normalised.push(range),
+            }
+        }
+
+        Self { ranges: normalised }
+    }
+
+    pub fn ranges(&self) -> &[Range<u64>] {
+        &self.ranges
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    /// The number of values in the set.
+    pub fn len(&self) -> u64 {
+        self.ranges.iter().map(|r| r.end - r.start).sum()
+    }
+
+    pub fn min(&self) -> Option<u64> {
+        self.ranges.first().map(|r| r.start)
+    }
+
+    pub fn contains(&self, value: u64) -> bool {
+        let idx = self.ranges.partition_point(|r| r.end <= value);
+
+        self.ranges.get(idx).is_some_and(|r| r.start <= value)
+    }
+
+    pub fn union(&self, other: &IntervalSet) -> IntervalSet {
+        Self::new(self.ranges.iter().chain(&other.ranges).cloned())
+    }
+
+    pub fn intersection(&self, other: &IntervalSet) -> IntervalSet {
+        Self::new(self.ranges.iter().flat_map(|r| other.overlapping(r)))
+    }
+
+    /// The parts of the set falling within the range.
+    fn overlapping<'a>(&'a self, range: &'a Range<u64>) -> impl Iterator<Item = Range<u64>> + 'a {
+        let first = self.ranges.partition_point(|r| r.end <= range.start);
+
+        self.ranges[first..]
+            .iter()
+            .take_while(|r| r.start < range.end)
+            .map(|r| max(r.start, range.start)..min(r.end, range.end))
+    }
+}
+
+impl From<Range<u64>> for IntervalSet {
+    fn from(range: Range<u64>) -> Self {
+        Self::new([range])
+    }
+}
+
+impl FromIterator<Range<u64>> for IntervalSet {
+    fn from_iter<T: IntoIterator<Item = Range<u64>>>(iter: T) -> Self {
+        Self::new(iter)
+    }
+}
+
+impl Display for IntervalSet {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let ranges = self
+            .ranges
+            .iter()
+            .map(|r| format!("{}..{}", r.start, r.end))
+            .join(", ");
+
+        write!(f, "{{{}}}", ranges)
+    }
+}
+
+/// A mapping of values which shifts ranges of values by a fixed offset each, any value outside those ranges maps to itself.
+///
+/// Internally the mapping is stored as sorted segments, without gaps in between, covering `0..u64::MAX`.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct IntervalMap {
+    segments: Vec<Segment>,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+struct Segment {
+    start: u64,
+    end: u64,
+    destination: u64,
+}
+
+impl Segment {
+    fn is_identity(&self) -> bool {
+        self.start == self.destination
+    }
+
+    fn destination_end(&self) -> u64 {
+        self.destination + (self.end - self.start)
+    }
+}
+
+impl IntervalMap {
+    /// Builds the map out of (source range, destination start) pairs, source ranges are not allowed to overlap.
+    pub fn new(mappings: impl IntoIterator<Item = (Range<u64>, u64)>) -> Self {
+        let mut segments = Vec::new();
+        let mut current = 0;
+
+        for (source, destination) in mappings
+            .into_iter()
+            .filter(|(s, _)| !s.is_empty())
+            .sorted_by_key(|(s, _)| s.start)
+        {
+            if source.start < current {
+                panic!("Overlapping mapping: {:?}", source);
+            }
+
+            segments.push(Segment {
+                start: current,
+                end: source.start,
+                destination: current,
+            });
+            segments.push(Segment {
+                start: source.start,
+                end: source.end,
+                destination,
+            });
+            current = source.end;
+        }
+
+        segments.push(Segment {
+            start: current,
+            end: u64::MAX,
+            destination: current,
+        });
+
+        Self::normalise(segments)
+    }
+
+    pub fn identity() -> Self {
+        Self::new([])
+    }
+
+    pub fn get(&self, value: u64) -> u64 {
+        let idx = self.segments.partition_point(|s| s.end <= value);
+
+        match self.segments.get(idx) {
+            Some(s) => value - s.start + s.destination,
+            None => value,
+        }
+    }
+
+    /// All (source range, destination range) pairs of values that do not map onto themselves.
+    pub fn mappings(&self) -> Vec<(Range<u64>, Range<u64>)> {
+        self.segments
+            .iter()
+            .filter(|s| !s.is_identity())
+            .map(|s| (s.start..s.end, s.destination..s.destination_end()))
+            .collect()
+    }
+
+    /// The set of values the given set maps onto.
+    pub fn image(&self, set: &IntervalSet) -> IntervalSet {
+        set.ranges()
+            .iter()
+            .flat_map(|r| self.restrict(r))
+            .map(|s| s.destination..s.destination_end())
+            .collect()
+    }
+
+    /// The set of all values mapping onto a value in the given set.
+    pub fn preimage(&self, set: &IntervalSet) -> IntervalSet {
+        self.segments
+            .iter()
+            .flat_map(|s| {
+                let destination = s.destination..s.destination_end();
+                set.overlapping(&destination)
+                    .map(|r| r.start - s.destination + s.start..r.end - s.destination + s.start)
+                    .collect_vec()
+            })
+            .collect()
+    }
+
+    /// The map applying this map first, followed by the other one.
+    pub fn compose(&self, other: &IntervalMap) -> IntervalMap {
+        let segments = self
+            .segments
+            .iter()
+            .flat_map(|s| {
+                other
+                    .restrict(&(s.destination..s.destination_end()))
+                    .map(|o| Segment {
+                        start: o.start - s.destination + s.start,
+                        end: o.end - s.destination + s.start,
+                        destination: o.destination,
+                    })
+                    .collect_vec()
+            })
+            .collect();
+
+        Self::normalise(segments)
+    }
+
+    /// The inverse map, only existing if every value is mapped onto by exactly one value.
+    pub fn invert(&self) -> Option<IntervalMap> {
+        let segments = self
+            .segments
+            .iter()
+            .map(|s| Segment {
+                start: s.destination,
+                end: s.destination_end(),
+                destination: s.start,
+            })
+            .sorted_by_key(|s| s.start)
+            .collect_vec();
+
+        let mut current = 0;
+        for segment in &segments {
+            if segment.start != current {
+                return None;
+            }
+            current = segment.end;
+        }
+
+        (current == u64::MAX).then(|| Self::normalise(segments))
+    }
+
+    /// The segments clipped to the range, with their destinations shifted accordingly.
+    fn restrict<'a>(&'a self, range: &'a Range<u64>) -> impl Iterator<Item = Segment> + 'a {
+        let first = self.segments.partition_point(|s| s.end <= range.start);
+
+        self.segments[first..]
+            .iter()
+            .take_while(|s| s.start < range.end)
+            .map(|s| {
+                let start = max(s.start, range.start);
+                Segment {
+                    start,
+                    end: min(s.end, range.end),
+                    destination: start - s.start + s.destination,
+                }
+            })
+    }
+
+    /// Drops empty segments and merges neighbouring segments sharing the same offset.
+    fn normalise(segments: Vec<Segment>) -> Self {
+        let mut normalised: Vec<Segment> = Vec::new();
+
+        for segment in segments.into_iter().filter(|s| s.start < s.end) {
+            match normalised.last_mut() {
+                Some(last) if last.destination_end() == segment.destination => {
+                    last.end = segment.end
+                }
+                _ => normalised.push(segment),
+            }
+        }
+
+        Self {
+            segments: normalised,
+        }
+    }
+}
+
+impl Display for IntervalMap {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let mappings = self
+            .mappings()
+            .iter()
+            .map(|(s, d)| format!("{}..{} -> {}..{}", s.start, s.end, d.start, d.end))
+            .join("\n");
+
+        write!(f, "{}", mappings)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn interval_set() {
+        let a = IntervalSet::new([5..10, 0..3, 3..4, 8..12, 20..20]);
+        let b = IntervalSet::new([2..6, 11..30]);
+
+        assert_eq!(&[0..4, 5..12], a.ranges());
+        assert_eq!(11, a.len());
+        assert!(a.contains(3) && !a.contains(4) && a.contains(11) && !a.contains(12));
+        assert_eq!(IntervalSet::from(0..30), a.union(&b));
+        assert_eq!(IntervalSet::new([2..4, 5..6, 11..12]), a.intersection(&b));
+        assert_eq!("{0..4, 5..12}", a.to_string());
+    }
+
+    #[test]
+    fn interval_map() {
+        let seed_to_soil = IntervalMap::new([(98..100, 50), (50..98, 52)]);
+        let soil_to_fertilizer = IntervalMap::new([(15..52, 0), (52..54, 37), (0..15, 39)]);
+
+        assert_eq!(81, seed_to_soil.get(79));
+        assert_eq!(14, seed_to_soil.get(14));
+        assert_eq!(
+            "50..98 -> 52..100\n98..100 -> 50..52",
+            seed_to_soil.to_string()
+        );
+
+        let composed = seed_to_soil.compose(&soil_to_fertilizer);
+        for seed in 0..200 {
+            assert_eq!(
+                soil_to_fertilizer.get(seed_to_soil.get(seed)),
+                composed.get(seed)
+            );
+        }
+
+        let inverted = composed.invert().unwrap();
+        for seed in 0..200 {
+            assert_eq!(seed, inverted.get(composed.get(seed)));
+        }
+        assert_eq!(IntervalMap::identity(), composed.compose(&inverted));
+
+        assert_eq!(
+            IntervalSet::new([14..15, 50..52, 81..82]),
+            seed_to_soil.image(&IntervalSet::new([14..15, 98..100, 79..80]))
+        );
+
+        // Both 10 and 20 map onto 20, so there is no inverse
+        let merging = IntervalMap::new([(10..11, 20)]);
+        assert_eq!(None, merging.invert());
+        assert_eq!(
+            IntervalSet::new([10..11, 20..21]),
+            merging.preimage(&IntervalSet::from(20..21))
+        );
+    }
+}